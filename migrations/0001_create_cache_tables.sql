@@ -0,0 +1,20 @@
+-- Tables as created before schema versioning; databases from that time
+-- already have them.
+CREATE TABLE IF NOT EXISTS cached_users (
+    username TEXT PRIMARY KEY,
+    user_data TEXT NOT NULL,
+    repositories TEXT NOT NULL,
+    events TEXT NOT NULL,
+    pull_requests TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cached_scores (
+    username TEXT PRIMARY KEY,
+    score TEXT NOT NULL,
+    rating TEXT NOT NULL,
+    stats TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    languages TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
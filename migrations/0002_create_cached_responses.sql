@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS cached_responses (
+    url TEXT PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    body TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
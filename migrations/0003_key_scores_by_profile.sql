@@ -0,0 +1,17 @@
+-- Scores are only a cache, so they are dropped rather than converted; old
+-- rows have no profile or model version to carry over.
+DROP TABLE IF EXISTS cached_scores;
+
+CREATE TABLE cached_scores (
+    username TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    profile_version TEXT NOT NULL,
+    model_version TEXT NOT NULL,
+    score TEXT NOT NULL,
+    rating TEXT NOT NULL,
+    stats TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    languages TEXT NOT NULL,
+    last_updated TEXT NOT NULL,
+    PRIMARY KEY (username, profile)
+);
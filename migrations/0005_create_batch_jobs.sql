@@ -0,0 +1,22 @@
+-- Lists of users scored in the background, kept so jobs survive a restart.
+CREATE TABLE batch_jobs (
+    id TEXT PRIMARY KEY,
+    profile TEXT NOT NULL,
+    window_days INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE batch_items (
+    job_id TEXT NOT NULL REFERENCES batch_jobs (id),
+    position INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    status TEXT NOT NULL,
+    final_score REAL,
+    rating TEXT,
+    component_scores TEXT,
+    error TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (job_id, position)
+);
+
+CREATE INDEX batch_items_by_status ON batch_items (status);
@@ -0,0 +1,2 @@
+-- Pagination links of the cached response, reused when a 304 omits them.
+ALTER TABLE cached_responses ADD COLUMN link TEXT;
@@ -0,0 +1,18 @@
+-- Scores for different activity windows are cached side by side instead of
+-- overwriting each other. They are only a cache, so old rows are dropped.
+DROP TABLE IF EXISTS cached_scores;
+
+CREATE TABLE cached_scores (
+    username TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    window_days INTEGER NOT NULL,
+    profile_version TEXT NOT NULL,
+    model_version TEXT NOT NULL,
+    score TEXT NOT NULL,
+    rating TEXT NOT NULL,
+    stats TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    languages TEXT NOT NULL,
+    last_updated TEXT NOT NULL,
+    PRIMARY KEY (username, profile, window_days)
+);
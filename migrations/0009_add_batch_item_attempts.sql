@@ -0,0 +1,3 @@
+-- Items whose scoring failed on a GitHub server or network error are put
+-- back for another try; attempts caps how often that happens.
+ALTER TABLE batch_items ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
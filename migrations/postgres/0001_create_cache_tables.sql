@@ -0,0 +1,33 @@
+-- Postgres support starts from SQLite schema version 3, so its history begins
+-- with the tables as they are there. Documents and timestamps are kept as
+-- text, like in SQLite, so rows decode the same way on both backends.
+CREATE TABLE IF NOT EXISTS cached_users (
+    username TEXT PRIMARY KEY,
+    user_data TEXT NOT NULL,
+    repositories TEXT NOT NULL,
+    events TEXT NOT NULL,
+    pull_requests TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cached_scores (
+    username TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    profile_version TEXT NOT NULL,
+    model_version TEXT NOT NULL,
+    score TEXT NOT NULL,
+    rating TEXT NOT NULL,
+    stats TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    languages TEXT NOT NULL,
+    last_updated TEXT NOT NULL,
+    PRIMARY KEY (username, profile)
+);
+
+CREATE TABLE IF NOT EXISTS cached_responses (
+    url TEXT PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    body TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
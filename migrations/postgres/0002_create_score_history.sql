@@ -0,0 +1,12 @@
+-- Every computed score, kept after the cached score is replaced.
+CREATE TABLE score_history (
+    id BIGSERIAL PRIMARY KEY,
+    username TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    model_version TEXT NOT NULL,
+    final_score DOUBLE PRECISION NOT NULL,
+    component_scores TEXT NOT NULL,
+    computed_at TEXT NOT NULL
+);
+
+CREATE INDEX score_history_by_user ON score_history (username, profile, computed_at);
@@ -0,0 +1,6 @@
+-- Workers claim an item in the database before scoring it, so replicas
+-- sharing the database don't score the same item. A claim that is not
+-- finished within its lease is taken over, so items whose worker died are
+-- scored again. Items put back after a failure wait for next_attempt_at.
+ALTER TABLE batch_items ADD COLUMN claimed_at TEXT;
+ALTER TABLE batch_items ADD COLUMN next_attempt_at TEXT;
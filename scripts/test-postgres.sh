@@ -0,0 +1,20 @@
+#!/usr/bin/env bash
+# Runs the test suite with the postgres feature against a throwaway Postgres
+# server in a temporary directory. Needs initdb and pg_ctl on PATH.
+set -euo pipefail
+
+PORT="${PGPORT:-54329}"
+DATA_DIR="$(mktemp -d)"
+
+cleanup() {
+    pg_ctl -D "$DATA_DIR" -m immediate stop >/dev/null 2>&1 || true
+    rm -rf "$DATA_DIR"
+}
+trap cleanup EXIT
+
+initdb -D "$DATA_DIR" -U postgres --auth=trust >/dev/null
+pg_ctl -D "$DATA_DIR" -o "-p $PORT -k $DATA_DIR -c listen_addresses=127.0.0.1" -w start >/dev/null
+
+export TEST_POSTGRES_URL="postgres://postgres@127.0.0.1:$PORT/postgres"
+# The Postgres tests are ignored unless asked for
+cargo test --features postgres "$@" -- --include-ignored
@@ -2,40 +2,243 @@ use axum::{
     routing::{post, get},
     Router,
     Json,
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use github_score_api::scoring::{GitHubScorer, GitHubUser, DetailedScores};
-use reqwest;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{mpsc, Notify, Semaphore};
+use github_score_api::scoring::{
+    compare, downsample, Clock, Comparison, GitHubUser, DetailedScores, HistoryInterval, HistoryPoint, ScoringConfig, ScoringError,
+    ScoringProfile, ScoringProfiles, SystemClock, DEFAULT_PROFILE,
+};
+use github_score_api::github::{
+    parse_lenient, DataSource, FetchError, GitHubClient, DEFAULT_BASE_URL, DEFAULT_CONCURRENCY,
+    DEFAULT_MAX_PULL_REQUESTS,
+};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
-use github_score_api::db::{Database, models::{CachedUser, CachedScore}};
-use chrono::Utc;
-use env_logger;
-use tower_http::cors::{CorsLayer, Any};
-
-#[cfg(feature = "shuttle")]
-use shuttle_axum::ShuttleAxum;
+use github_score_api::db::{
+    CacheTtl, Database, DbError, DbOptions,
+    models::{BatchItem, BatchItemStatus, BatchJob, CachedUser, CachedScore, ScoreHistoryEntry},
+};
+use chrono::{DateTime, Utc};
+use tower_http::cors::CorsLayer;
 #[cfg(feature = "shuttle")]
-use shuttle_axum::AxumService;
+use tower_http::cors::Any;
 
-#[derive(Clone)]
 struct AppState {
-    scorer: Arc<GitHubScorer>,
-    client: Arc<reqwest::Client>,
+    profiles: Arc<ScoringProfiles>,
+    github: Arc<GitHubClient>,
     db: Arc<Database>,
+    /// Reference time for scores and activity windows.
+    clock: Arc<dyn Clock>,
+    refresh_tx: mpsc::Sender<RefreshJob>,
+    /// (username, profile) pairs with a refresh queued or running.
+    refreshing: Mutex<HashSet<(String, String, u32)>>,
+    /// Wakes the batch worker when a job is created or an item finishes.
+    batch_wake: Notify,
+}
+
+impl AppState {
+    /// Builds the state and starts the background refresh and batch workers.
+    fn new(
+        profiles: Arc<ScoringProfiles>,
+        github: Arc<GitHubClient>,
+        db: Arc<Database>,
+        clock: Arc<dyn Clock>,
+    ) -> Arc<Self> {
+        let (refresh_tx, refresh_rx) = mpsc::channel(REFRESH_QUEUE_CAPACITY);
+        let state = Arc::new(Self {
+            profiles,
+            github,
+            db,
+            clock,
+            refresh_tx,
+            refreshing: Mutex::new(HashSet::new()),
+            batch_wake: Notify::new(),
+        });
+        tokio::spawn(run_refresh_worker(Arc::downgrade(&state), refresh_rx));
+        tokio::spawn(run_batch_worker(Arc::downgrade(&state)));
+        state
+    }
+}
+
+/// A stale user whose data and score should be fetched again.
+#[derive(Debug)]
+struct RefreshJob {
+    username: String,
+    profile: String,
+    window_days: u32,
 }
 
+/// Refreshes beyond this many waiting are dropped; the next stale hit queues them again.
+const REFRESH_QUEUE_CAPACITY: usize = 256;
+
+/// Background refreshes running at once, to keep GitHub rate limits for requests.
+const REFRESH_CONCURRENCY: usize = 2;
+
+/// Activity stats cover the last 30 days unless the request asks otherwise.
+const DEFAULT_ACTIVITY_WINDOW_DAYS: u32 = 30;
+
+/// GitHub's events API only returns events from the last 90 days.
+const MAX_ACTIVITY_WINDOW_DAYS: u32 = 90;
+
+/// `refresh=true` is ignored for scores computed less than this many seconds
+/// ago, so clients can't spend the server's GitHub rate budget at will.
+const MIN_REFRESH_AGE_SECS: u64 = 300;
+
+/// GitHub logins are at most 39 characters long.
+const MAX_USERNAME_LENGTH: usize = 39;
+
+/// Users one comparison can take; each may cost a full fetch from GitHub.
+const MIN_COMPARE_USERS: usize = 2;
+const MAX_COMPARE_USERS: usize = 10;
+
+/// Users one batch job can take.
+const MAX_BATCH_USERS: usize = 500;
+
+/// Batch items scored at once.
+const BATCH_CONCURRENCY: usize = 2;
+
+/// Batch jobs pause while any GitHub rate limit is down to this percentage of
+/// its budget, which is left for interactive requests.
+const BATCH_RATE_RESERVE_PERCENT: u64 = 20;
+
+/// How long the batch worker sleeps when there is nothing to do, in case a
+/// wake-up was missed, and after a database error.
+const BATCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long a claimed batch item stays with its worker. Items still running
+/// after this, because their replica died, are handed to another worker.
+const BATCH_LEASE_MINUTES: i64 = 10;
+
+/// How long a batch item waits before another try after its job couldn't be
+/// read, or after its first failure on a GitHub server or network error.
+/// Later tries after such failures wait twice as long each time.
+const BATCH_RETRY_DELAY_SECS: i64 = 60;
+
+/// Tries a batch item gets when scoring it keeps failing on GitHub server or
+/// network errors, after which it is marked failed.
+const BATCH_MAX_ATTEMPTS: u32 = 4;
+
+/// How often the scoring profile directory is checked for changes.
+const DEFAULT_PROFILE_POLL_SECS: u64 = 5;
+
 #[derive(Debug, Deserialize)]
 struct ScoreRequest {
     username: String,
+    #[serde(default)]
+    window_days: Option<u32>,
+    /// Scoring profile to use; `default` when omitted.
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompareRequest {
+    usernames: Vec<String>,
+    #[serde(default)]
+    window_days: Option<u32>,
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CompareResponse {
+    profile: String,
+    window_days: u32,
+    #[serde(flatten)]
+    comparison: Comparison,
+    /// Each user's full score, by username.
+    scores: BTreeMap<String, ScoreResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    usernames: Vec<String>,
+    #[serde(default)]
+    window_days: Option<u32>,
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum BatchState {
+    /// No user has been scored yet.
+    Queued,
+    Running,
+    /// Every user has been scored or has failed.
+    Completed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchResponse {
+    id: String,
+    profile: String,
+    window_days: u32,
+    created_at: DateTime<Utc>,
+    status: BatchState,
+    total: usize,
+    done: usize,
+    failed: usize,
+    /// Every user in submission order, with a score once it is computed.
+    results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchResult {
+    username: String,
+    status: BatchItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    final_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component_scores: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    /// Unreadable cache rows found and deleted since startup.
+    corrupt_rows: u64,
+}
+
+/// Options for `GET /api/score/{username}`.
+#[derive(Debug, Default, Deserialize)]
+struct ScoreQuery {
+    profile: Option<String>,
+    #[serde(alias = "window_days")]
+    window: Option<u32>,
+    /// Fetch fresh data from GitHub instead of serving the cache.
+    #[serde(default)]
+    refresh: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ScoreResponse {
+    profile: String,
+    profile_version: String,
+    /// Scoring algorithm and config the score was computed with.
+    model_version: String,
+    /// Served from an expired cache entry while a refresh runs in the background.
+    #[serde(default)]
+    stale: bool,
+    /// Seconds since the score (or the data behind a stale score) was computed.
+    #[serde(default)]
+    age_seconds: u64,
+    /// When the score was computed; the `ETag` of GET responses.
+    #[serde(skip)]
+    last_updated: DateTime<Utc>,
     score: DetailedScores,
     rating: String,
     stats: UserStats,
@@ -43,6 +246,25 @@ struct ScoreResponse {
     languages: LanguageDistribution,
 }
 
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    from: Option<String>,
+    to: Option<String>,
+    /// `raw` (default), `weekly` or `monthly`.
+    interval: Option<String>,
+    profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryResponse {
+    username: String,
+    profile: String,
+    interval: HistoryInterval,
+    from: Option<DateTime<Utc>>,
+    to: DateTime<Utc>,
+    points: Vec<HistoryPoint>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct UserStats {
     total_repositories: usize,
@@ -53,9 +275,18 @@ struct UserStats {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ActivityData {
-    commits_last_month: usize,
-    pull_requests_last_month: usize,
-    issues_last_month: usize,
+    /// Length of the window the counts below cover, in days.
+    #[serde(default)]
+    window_days: u32,
+    /// Number of fetched events that fell inside the window.
+    #[serde(default)]
+    events_in_window: usize,
+    #[serde(alias = "commits_last_month")]
+    commits: usize,
+    #[serde(alias = "pull_requests_last_month")]
+    pull_requests: usize,
+    #[serde(alias = "issues_last_month")]
+    issues: usize,
     activity_trend: Vec<ActivityPoint>,
 }
 
@@ -75,48 +306,223 @@ struct LanguageDistribution {
 #[derive(Debug, Serialize)]
 struct GitHubError {
     message: String,
+    /// Seconds until GitHub will accept requests again; only set on 429s.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubRateLimitError {
-    message: String,
-    #[allow(dead_code)]
-    documentation_url: Option<String>,
-}
-
-async fn handle_github_response<T: for<'de> serde::Deserialize<'de>>(
-    response: reqwest::Response,
-) -> Result<T, (StatusCode, Json<GitHubError>)> {
-    let status = response.status();
-    let text = response.text().await.map_err(|e| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(GitHubError { message: format!("Failed to read response: {}", e) })
-    ))?;
-
-    if !status.is_success() {
-        // Try to parse as GitHub error response
-        if let Ok(error) = serde_json::from_str::<GitHubRateLimitError>(&text) {
-            return Err((
-                StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
-                Json(GitHubError { 
-                    message: format!("GitHub API error: {}", error.message) 
-                })
-            ));
+type ApiError = (StatusCode, Json<GitHubError>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(GitHubError { message: message.into(), retry_after: None }))
+}
+
+fn fetch_error_response(error: FetchError) -> ApiError {
+    let (status, retry_after) = match &error {
+        FetchError::RateLimited { retry_after, .. } => {
+            (StatusCode::TOO_MANY_REQUESTS, Some(*retry_after))
         }
-        return Err((
-            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
-            Json(GitHubError { 
-                message: format!("GitHub API error: {}", text) 
-            })
-        ));
-    }
+        FetchError::Api { status, .. } => (
+            StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            None,
+        ),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, None),
+    };
+    (status, Json(GitHubError { message: error.to_string(), retry_after }))
+}
+
+fn to_values<T: Serialize>(items: &[T]) -> Vec<Value> {
+    items.iter()
+        .filter_map(|item| serde_json::to_value(item).ok())
+        .collect()
+}
+
+/// Loads the base scoring model from the file named by SCORING_CONFIG (built-in
+/// defaults otherwise) with DISTINCT_COMMITS_ONLY, DECAY_FUNCTION,
+/// DECAY_HALF_LIFE_DAYS and DECAY_FLOOR applied on top. It serves as the
+/// `default` profile unless SCORING_PROFILES_DIR has its own `default.toml`.
+fn load_profiles() -> Result<ScoringProfiles, ScoringError> {
+    let config = match env::var("SCORING_CONFIG") {
+        Ok(path) => {
+            println!("Loading scoring config from {}", path);
+            ScoringConfig::from_file(path)?
+        }
+        Err(_) => ScoringConfig::default(),
+    };
+    let config = apply_scoring_overrides(config, |name| env::var(name).ok())?;
 
-    serde_json::from_str(&text).map_err(|e| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(GitHubError { 
-            message: format!("Failed to parse response: {}", e) 
+    let profiles = match env::var("SCORING_PROFILES_DIR") {
+        Ok(dir) => {
+            println!("Loading scoring profiles from {}", dir);
+            ScoringProfiles::from_dir(dir, config)?
+        }
+        Err(_) => ScoringProfiles::single(config)?,
+    };
+    println!("Scoring profiles: {}", profiles.names().join(", "));
+    Ok(profiles)
+}
+
+/// Applies the scoring overrides `var` returns over `config`. A value that
+/// does not parse, or leaves the config invalid, is an error rather than
+/// being ignored.
+fn apply_scoring_overrides(
+    mut config: ScoringConfig,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<ScoringConfig, ScoringError> {
+    fn parse<T: std::str::FromStr>(name: &str, value: String) -> Result<T, ScoringError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        value.parse().map_err(|e: T::Err| ScoringError::InvalidOverride {
+            name: name.to_string(),
+            message: e.to_string(),
+            value,
         })
-    ))
+    }
+
+    if let Some(v) = var("DISTINCT_COMMITS_ONLY") {
+        config.distinct_commits_only = match v.to_ascii_lowercase().as_str() {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => {
+                return Err(ScoringError::InvalidOverride {
+                    name: "DISTINCT_COMMITS_ONLY".to_string(),
+                    message: "expected 'true' or 'false'".to_string(),
+                    value: v,
+                })
+            }
+        };
+    }
+    if let Some(v) = var("DECAY_FUNCTION") {
+        config.time_decay.function = parse("DECAY_FUNCTION", v)?;
+    }
+    if let Some(v) = var("DECAY_HALF_LIFE_DAYS") {
+        config.time_decay.half_life_days = parse("DECAY_HALF_LIFE_DAYS", v)?;
+    }
+    if let Some(v) = var("DECAY_FLOOR") {
+        config.time_decay.floor = parse("DECAY_FLOOR", v)?;
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+/// An environment variable set to a value the server can't use.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid {name} '{value}': {message}")]
+struct ConfigError {
+    name: String,
+    value: String,
+    message: String,
+}
+
+impl ConfigError {
+    fn new(name: &str, value: String, message: impl Into<String>) -> Self {
+        Self { name: name.to_string(), value, message: message.into() }
+    }
+}
+
+/// Parses the variable `name` that `var` returns, or `None` if it isn't set.
+/// A value that doesn't parse is an error rather than being ignored.
+fn parse_var<T: std::str::FromStr>(var: &impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    var(name)
+        .map(|value| value.parse().map_err(|e: T::Err| ConfigError::new(name, value, e.to_string())))
+        .transpose()
+}
+
+/// Stops the server with a message when its configuration can't be used.
+fn exit_on_config_error<T>(result: Result<T, impl std::fmt::Display>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1)
+    })
+}
+
+/// Loads the scoring profiles and starts reloading them in the background.
+fn start_profiles() -> Arc<ScoringProfiles> {
+    let profiles = Arc::new(exit_on_config_error(load_profiles()));
+    let poll_secs = env::var("SCORING_PROFILES_POLL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_PROFILE_POLL_SECS);
+    tokio::spawn(profiles.clone().watch(std::time::Duration::from_secs(poll_secs.max(1))));
+    profiles
+}
+
+/// Reads CACHE_USER_TTL_SECS, CACHE_SCORE_TTL_SECS and
+/// CACHE_STALE_WHILE_REVALIDATE_SECS from `var` over the defaults.
+fn cache_ttl_from_env(var: impl Fn(&str) -> Option<String>) -> Result<CacheTtl, ConfigError> {
+    // Unsigned, so a negative TTL that would make every entry stale is rejected
+    let seconds = |name: &str| -> Result<Option<chrono::Duration>, ConfigError> {
+        let secs: Option<u32> = parse_var(&var, name)?;
+        Ok(secs.map(|secs| chrono::Duration::seconds(secs.into())))
+    };
+    let defaults = CacheTtl::default();
+    Ok(CacheTtl {
+        user_data: seconds("CACHE_USER_TTL_SECS")?.unwrap_or(defaults.user_data),
+        scores: seconds("CACHE_SCORE_TTL_SECS")?.unwrap_or(defaults.scores),
+        stale_while_revalidate: seconds("CACHE_STALE_WHILE_REVALIDATE_SECS")?
+            .unwrap_or(defaults.stale_while_revalidate),
+    })
+}
+
+/// Reads DATABASE_MAX_CONNECTIONS, DATABASE_BUSY_TIMEOUT_MS and DATABASE_WAL
+/// from `var` over the defaults.
+fn db_options_from_env(var: impl Fn(&str) -> Option<String>) -> Result<DbOptions, ConfigError> {
+    let defaults = DbOptions::default();
+    let max_connections = parse_var(&var, "DATABASE_MAX_CONNECTIONS")?.unwrap_or(defaults.max_connections);
+    if max_connections == 0 {
+        return Err(ConfigError::new("DATABASE_MAX_CONNECTIONS", "0".to_string(), "must be at least 1"));
+    }
+    let wal = match var("DATABASE_WAL") {
+        Some(v) => match v.to_ascii_lowercase().as_str() {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => return Err(ConfigError::new("DATABASE_WAL", v, "expected 'true' or 'false'")),
+        },
+        None => defaults.wal,
+    };
+    Ok(DbOptions {
+        max_connections,
+        busy_timeout: parse_var(&var, "DATABASE_BUSY_TIMEOUT_MS")?
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(defaults.busy_timeout),
+        wal,
+    })
+}
+
+/// Builds the GitHub client from the `GITHUB_*` variables `var` returns.
+fn build_github_client(
+    github_token: &str,
+    db: Arc<Database>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<GitHubClient, ConfigError> {
+    let base_url = var("GITHUB_API_URL").unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    let concurrency = parse_var(&var, "GITHUB_CONCURRENCY")?.unwrap_or(DEFAULT_CONCURRENCY);
+    if concurrency == 0 {
+        return Err(ConfigError::new("GITHUB_CONCURRENCY", concurrency.to_string(), "must be at least 1"));
+    }
+    let max_pull_requests = parse_var(&var, "GITHUB_MAX_PULL_REQUESTS")?.unwrap_or(DEFAULT_MAX_PULL_REQUESTS);
+    let mut data_source = match var("GITHUB_DATA_SOURCE") {
+        Some(v) => v
+            .parse::<DataSource>()
+            .map_err(|_| ConfigError::new("GITHUB_DATA_SOURCE", v, "expected 'rest' or 'graphql'"))?,
+        None => DataSource::default(),
+    };
+    if data_source == DataSource::GraphQl && github_token.is_empty() {
+        println!("WARNING: GraphQL needs GITHUB_TOKEN. Falling back to the REST data source.");
+        data_source = DataSource::Rest;
+    }
+    println!("GitHub data source: {:?}", data_source);
+    Ok(GitHubClient::new(Some(github_token))
+        .expect("Failed to build GitHub client")
+        .with_base_url(base_url)
+        .with_response_cache(db)
+        .with_concurrency(concurrency)
+        .with_max_pull_requests(max_pull_requests)
+        .with_data_source(data_source))
 }
 
 #[cfg(not(feature = "shuttle"))]
@@ -150,42 +556,24 @@ async fn main() {
     println!("GitHub Token: {}", if github_token.is_empty() { "Not set" } else { "Set" });
     
     // Initialize database
-    let db = Arc::new(Database::new().await.expect("Failed to initialize database"));
+    let db_options = exit_on_config_error(db_options_from_env(|name| env::var(name).ok()));
+    let cache_ttl = exit_on_config_error(cache_ttl_from_env(|name| env::var(name).ok()));
+    let db = Arc::new(
+        Database::connect(&Database::url_from_env(), db_options).await
+            .expect("Failed to initialize database")
+            .with_ttl(cache_ttl)
+    );
     
-    // Initialize GitHub scorer with token if available
-    let scorer = if !github_token.is_empty() {
-        Arc::new(GitHubScorer::with_token(github_token.clone()))
-    } else {
-        Arc::new(GitHubScorer::new())
-    };
+    // Initialize scoring profiles
+    let profiles = start_profiles();
     
-    // Initialize HTTP client with GitHub token if available
-    let client = if !github_token.is_empty() {
-        Arc::new(reqwest::Client::builder()
-            .user_agent("github-score-api")
-            .default_headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", github_token).parse().unwrap()
-                );
-                headers
-            })
-            .build()
-            .expect("Failed to build HTTP client"))
-    } else {
-        Arc::new(reqwest::Client::builder()
-            .user_agent("github-score-api")
-            .build()
-            .expect("Failed to build HTTP client"))
-    };
+    // Initialize GitHub client with token if available
+    let github = Arc::new(exit_on_config_error(build_github_client(&github_token, db.clone(), |name| {
+        env::var(name).ok()
+    })));
     
     // Create app state
-    let state = Arc::new(AppState {
-        scorer,
-        client,
-        db,
-    });
+    let state = AppState::new(profiles, github, db, Arc::new(SystemClock));
     
     // Configure CORS
     let cors = CorsLayer::new()
@@ -197,6 +585,12 @@ async fn main() {
     // Build router
     let app = Router::new()
         .route("/api/score", post(score_user))
+        .route("/api/score/{username}", get(score_user_by_path))
+        .route("/api/compare", post(compare_users))
+        .route("/api/batch", post(create_batch))
+        .route("/api/batch/{id}", get(batch_status))
+        .route("/api/batch/{id}/results.csv", get(batch_results_csv))
+        .route("/api/users/{username}/history", get(score_history))
         .route("/api/health", get(health_check))
         .layer(cors)
         .with_state(state);
@@ -230,42 +624,24 @@ async fn shuttle_main() -> shuttle_axum::ShuttleAxum {
     println!("GitHub Token: {}", if github_token.is_empty() { "Not set" } else { "Set" });
     
     // Initialize database
-    let db = Arc::new(Database::new().await.expect("Failed to initialize database"));
+    let db_options = exit_on_config_error(db_options_from_env(|name| env::var(name).ok()));
+    let cache_ttl = exit_on_config_error(cache_ttl_from_env(|name| env::var(name).ok()));
+    let db = Arc::new(
+        Database::connect(&Database::url_from_env(), db_options).await
+            .expect("Failed to initialize database")
+            .with_ttl(cache_ttl)
+    );
     
-    // Initialize GitHub scorer with token if available
-    let scorer = if !github_token.is_empty() {
-        Arc::new(GitHubScorer::with_token(github_token.clone()))
-    } else {
-        Arc::new(GitHubScorer::new())
-    };
+    // Initialize scoring profiles
+    let profiles = start_profiles();
     
-    // Initialize HTTP client with GitHub token if available
-    let client = if !github_token.is_empty() {
-        Arc::new(reqwest::Client::builder()
-            .user_agent("github-score-api")
-            .default_headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", github_token).parse().unwrap()
-                );
-                headers
-            })
-            .build()
-            .expect("Failed to build HTTP client"))
-    } else {
-        Arc::new(reqwest::Client::builder()
-            .user_agent("github-score-api")
-            .build()
-            .expect("Failed to build HTTP client"))
-    };
+    // Initialize GitHub client with token if available
+    let github = Arc::new(exit_on_config_error(build_github_client(&github_token, db.clone(), |name| {
+        env::var(name).ok()
+    })));
     
     // Create app state
-    let state = Arc::new(AppState {
-        scorer,
-        client,
-        db,
-    });
+    let state = AppState::new(profiles, github, db, Arc::new(SystemClock));
     
     // Configure CORS
     let cors = CorsLayer::new()
@@ -277,6 +653,12 @@ async fn shuttle_main() -> shuttle_axum::ShuttleAxum {
     // Build router
     let app = Router::new()
     .route("/api/score", post(score_user))
+    .route("/api/score/{username}", get(score_user_by_path))
+    .route("/api/compare", post(compare_users))
+    .route("/api/batch", post(create_batch))
+    .route("/api/batch/{id}", get(batch_status))
+    .route("/api/batch/{id}/results.csv", get(batch_results_csv))
+    .route("/api/users/{username}/history", get(score_history))
     .route("/api/health", get(health_check))
         .layer(cors)
         .with_state(state);
@@ -287,218 +669,417 @@ async fn shuttle_main() -> shuttle_axum::ShuttleAxum {
 async fn score_user(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<ScoreRequest>,
-) -> Result<Json<ScoreResponse>, (StatusCode, Json<GitHubError>)> {
+) -> Result<Json<ScoreResponse>, ApiError> {
     println!("Received request for username: {}", payload.username);
 
-    // Check cache first
-    match state.db.get_cached_score(&payload.username).await {
+    validate_username(&payload.username)?;
+    let (profile, window_days) = score_options(&state, payload.profile.as_deref(), payload.window_days)?;
+    get_score(&state, &payload.username, &profile, window_days).await.map(Json)
+}
+
+/// `GET` form of [`score_user`], with `ETag` and `Cache-Control` headers so
+/// browsers and CDNs can cache the response.
+async fn score_user_by_path(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    Query(query): Query<ScoreQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    println!("Received request for username: {}", username);
+
+    validate_username(&username)?;
+    let (profile, window_days) = score_options(&state, query.profile.as_deref(), query.window)?;
+    let refresh = query.refresh && !recently_scored(&state, &username, &profile, window_days).await;
+    if query.refresh && !refresh {
+        println!("Ignoring refresh of {}: scored less than {}s ago", username, MIN_REFRESH_AGE_SECS);
+    }
+    let response = if refresh {
+        let user = fetch_user(&state, &username).await?;
+        compute_score(&state, &username, &user, &profile, window_days).await?
+    } else {
+        get_score(&state, &username, &profile, window_days).await?
+    };
+
+    let etag = score_etag(&response);
+    let cache_control = score_cache_control(state.db.ttl(), &response);
+    let not_modified = headers.get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+
+    let mut http_response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Json(response).into_response()
+    };
+    let response_headers = http_response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        response_headers.insert(CACHE_CONTROL, value);
+    }
+    Ok(http_response)
+}
+
+/// Scores several users at once and compares them: ranking, per-component
+/// deltas and the sub-metrics that set each user apart.
+async fn compare_users(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CompareRequest>,
+) -> Result<Json<CompareResponse>, ApiError> {
+    let usernames = distinct_usernames(&payload.usernames);
+    if !(MIN_COMPARE_USERS..=MAX_COMPARE_USERS).contains(&usernames.len()) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("usernames must name between {} and {} distinct users", MIN_COMPARE_USERS, MAX_COMPARE_USERS),
+        ));
+    }
+    for username in &usernames {
+        validate_username(username)?;
+    }
+    let (profile, window_days) = score_options(&state, payload.profile.as_deref(), payload.window_days)?;
+    println!("Received comparison request for: {}", usernames.join(", "));
+
+    let results = futures::future::join_all(
+        usernames.iter().map(|username| get_score(&state, username, &profile, window_days)),
+    )
+    .await;
+    let mut scores = Vec::with_capacity(usernames.len());
+    for (username, result) in usernames.into_iter().zip(results) {
+        match result {
+            Ok(response) => scores.push((username, response)),
+            Err((status, Json(mut error))) => {
+                error.message = format!("{}: {}", username, error.message);
+                return Err((status, Json(error)));
+            }
+        }
+    }
+
+    let comparison = compare(&scores.iter().map(|(username, r)| (username.as_str(), &r.score)).collect::<Vec<_>>());
+    Ok(Json(CompareResponse {
+        profile: profile.name.clone(),
+        window_days,
+        comparison,
+        scores: scores.into_iter().collect(),
+    }))
+}
+
+/// The usernames trimmed, without blanks and case-insensitive repeats, in
+/// the order given.
+fn distinct_usernames(usernames: &[String]) -> Vec<String> {
+    let mut distinct: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for username in usernames {
+        let username = username.trim();
+        if !username.is_empty() && seen.insert(username.to_ascii_lowercase()) {
+            distinct.push(username.to_string());
+        }
+    }
+    distinct
+}
+
+/// Rejects anything that can't be a GitHub login (letters, digits and
+/// hyphens, at most 39 characters) before it ends up in a GitHub API path.
+fn validate_username(username: &str) -> Result<(), ApiError> {
+    let valid = (1..=MAX_USERNAME_LENGTH).contains(&username.len())
+        && username.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+    if valid {
+        Ok(())
+    } else {
+        Err(api_error(StatusCode::BAD_REQUEST, format!("'{}' is not a valid GitHub username", username)))
+    }
+}
+
+/// Whether the cached score is from the current model and younger than
+/// [`MIN_REFRESH_AGE_SECS`], in which case a refresh is not worth a fetch.
+async fn recently_scored(state: &AppState, username: &str, profile: &ScoringProfile, window_days: u32) -> bool {
+    matches!(
+        state.db.get_cached_score(username, &profile.name, window_days).await,
+        Ok(Some(cached)) if cached.model_version == profile.model_version
+            && age_seconds(cached.last_updated) < MIN_REFRESH_AGE_SECS
+    )
+}
+
+/// Checks the requested window and looks up the profile, `default` when none
+/// is named.
+fn score_options(
+    state: &AppState,
+    profile: Option<&str>,
+    window_days: Option<u32>,
+) -> Result<(Arc<ScoringProfile>, u32), ApiError> {
+    let window_days = window_days.unwrap_or(DEFAULT_ACTIVITY_WINDOW_DAYS);
+    if !(1..=MAX_ACTIVITY_WINDOW_DAYS).contains(&window_days) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("window_days must be between 1 and {}", MAX_ACTIVITY_WINDOW_DAYS),
+        ));
+    }
+
+    let profile = state.profiles.get(profile.unwrap_or(DEFAULT_PROFILE))
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok((profile, window_days))
+}
+
+/// A weak validator, since `age_seconds` changes while the score does not.
+/// The profile and window are part of the URL, so the time the score was
+/// computed is enough to tell versions apart.
+fn score_etag(response: &ScoreResponse) -> String {
+    format!("W/\"{}\"", response.last_updated.timestamp_millis())
+}
+
+/// Whether an `If-None-Match` header names `etag`, compared weakly.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// Fresh scores may be cached for the rest of their TTL. Stale ones were
+/// served while a refresh runs, so caches have to come back for the new one.
+fn score_cache_control(ttl: CacheTtl, response: &ScoreResponse) -> String {
+    if response.stale {
+        return "no-cache".to_string();
+    }
+    let remaining = (ttl.scores.num_seconds() - response.age_seconds as i64).max(0);
+    let swr = ttl.stale_while_revalidate.num_seconds();
+    if swr > 0 {
+        format!("public, max-age={}, stale-while-revalidate={}", remaining, swr)
+    } else {
+        format!("public, max-age={}", remaining)
+    }
+}
+
+/// Serves a score from the cache when one matches the profile, model and
+/// window, and computes it otherwise. Stale cache entries are served as they
+/// are, marked `stale`, while a refresh is queued.
+async fn get_score(
+    state: &Arc<AppState>,
+    username: &str,
+    profile: &ScoringProfile,
+    window_days: u32,
+) -> Result<ScoreResponse, ApiError> {
+    match state.db.get_cached_score(username, &profile.name, window_days).await {
+        Ok(Some(cached_score)) if cached_score.model_version != profile.model_version => {
+            println!("Cached score for user {} is from model {}, current is {}",
+                username,
+                cached_score.model_version,
+                profile.model_version
+            );
+        }
         Ok(Some(cached_score)) => {
             println!("Found cached score for user: {} (last updated: {})", 
-                payload.username, 
+                username, 
                 cached_score.last_updated
             );
-            return Ok(Json(ScoreResponse {
-                score: serde_json::from_value(cached_score.score)
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to parse cached score: {}", e) })
-                    ))?,
-                rating: cached_score.rating,
-                stats: serde_json::from_value(cached_score.stats)
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to parse cached stats: {}", e) })
-                    ))?,
-                activity: serde_json::from_value(cached_score.activity)
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to parse cached activity: {}", e) })
-                    ))?,
-                languages: serde_json::from_value(cached_score.languages)
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to parse cached languages: {}", e) })
-                    ))?,
-            }));
+            let stale = CacheTtl::is_stale(state.db.ttl().scores, cached_score.last_updated);
+            match cached_score_response(cached_score) {
+                Ok(mut response) => {
+                    if stale {
+                        response.stale = true;
+                        queue_refresh(state, username, &profile.name, window_days);
+                    }
+                    return Ok(response);
+                }
+                // Recomputing overwrites the unreadable entry
+                Err(e) => println!("Ignoring unreadable cached score for user {}: {}", username, e),
+            }
         }
         Ok(None) => {
-            println!("No cached score found for user: {}", payload.username);
+            println!("No cached score found for user: {}", username);
         }
         Err(e) => {
-            println!("Error checking cache for user {}: {}", payload.username, e);
+            println!("Error checking cache for user {}: {}", username, e);
         }
     }
 
     // Check if we have cached user data
-    let (_user_data, all_repos, events, pulls) = match state.db.get_cached_user(&payload.username).await {
+    let user = match state.db.get_cached_user(username).await {
         Ok(Some(cached_user)) => {
             println!("Found cached user data for: {} (last updated: {})", 
-                payload.username, 
+                username, 
                 cached_user.last_updated
             );
-            (
-                cached_user.user_data,
-                cached_user.repositories,
-                cached_user.events,
-                cached_user.pull_requests,
-            )
-        }
-        Ok(None) => {
-            println!("No cached user data found for: {}", payload.username);
-            // Fetch fresh data from GitHub
-            let user_url = format!("https://api.github.com/users/{}", payload.username);
-            println!("Fetching user data from: {}", user_url);
-            
-            let user_response = state.client
-                .get(&user_url)
-                .send()
-                .await
-                .map_err(|e| {
-                    println!("Error fetching user data: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to fetch user data: {}", e) })
-                    )
-                })?;
-
-            println!("User response status: {}", user_response.status());
-
-            let user_data: Value = handle_github_response(user_response).await?;
-            println!("Successfully fetched user data");
-
-            // Fetch repositories with pagination
-            let mut all_repos = Vec::new();
-            let mut page = 1;
-            loop {
-                let repos_url = format!("https://api.github.com/users/{}/repos?per_page=100&page={}", payload.username, page);
-                println!("Fetching repositories from: {}", repos_url);
-                
-                let repos_response = state.client
-                    .get(&repos_url)
-                    .send()
-                    .await
-                    .map_err(|e| {
-                        println!("Error fetching repositories: {}", e);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(GitHubError { message: format!("Failed to fetch repositories: {}", e) })
-                        )
-                    })?;
-
-                println!("Repos response status: {}", repos_response.status());
-
-                let repos: Vec<Value> = handle_github_response(repos_response).await?;
-                println!("Successfully fetched {} repositories from page {}", repos.len(), page);
-                
-                if repos.is_empty() {
-                    break;
-                }
-                
-                all_repos.extend(repos);
-                page += 1;
-            }
-            println!("Total repositories fetched: {}", all_repos.len());
-
-            // Fetch events
-            let events_url = format!("https://api.github.com/users/{}/events?per_page=100", payload.username);
-            println!("Fetching events from: {}", events_url);
-            
-            let events_response = state.client
-                .get(&events_url)
-                .send()
-                .await
-                .map_err(|e| {
-                    println!("Error fetching events: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(GitHubError { message: format!("Failed to fetch events: {}", e) })
-                    )
-                })?;
-
-            println!("Events response status: {}", events_response.status());
-
-            let events: Vec<Value> = handle_github_response(events_response).await?;
-            println!("Successfully fetched {} events", events.len());
-
-            // Fetch pull requests for first 10 repos
-            let mut pulls = Vec::new();
-            for repo in all_repos.iter().take(10) {
-                if let Some(full_name) = repo["full_name"].as_str() {
-                    let pr_url = format!(
-                        "https://api.github.com/repos/{}/pulls?state=all&creator={}",
-                        full_name, payload.username
-                    );
-                    println!("Fetching PRs from: {}", pr_url);
-                    
-                    if let Ok(pr_response) = state.client
-                        .get(&pr_url)
-                        .send()
-                        .await 
-                    {
-                        println!("PR response status: {}", pr_response.status());
-                        if let Ok(prs_json) = handle_github_response::<Vec<Value>>(pr_response).await {
-                            let pr_count = prs_json.len();
-                            pulls.extend(prs_json);
-                            println!("Successfully fetched {} PRs from {}", pr_count, full_name);
-                        }
-                    }
-                }
-            }
-
-            // Cache the user data
-            let cached_user = CachedUser {
-                username: payload.username.clone(),
-                user_data: user_data.clone(),
-                repositories: all_repos.clone(),
-                events: events.clone(),
-                pull_requests: pulls.clone(),
-                last_updated: Utc::now(),
+            let stale_since = CacheTtl::is_stale(state.db.ttl().user_data, cached_user.last_updated)
+                .then_some(cached_user.last_updated);
+            let user = GitHubUser {
+                login: username.to_string(),
+                repositories: parse_lenient(cached_user.repositories, "repository"),
+                events: parse_lenient(cached_user.events, "event"),
+                pull_requests: parse_lenient(cached_user.pull_requests, "pull request"),
+                contributions: serde_json::from_value(cached_user.user_data["contributions"].clone())
+                    .ok()
+                    .flatten(),
             };
-            
-            if let Err(e) = state.db.cache_user(&cached_user).await {
-                println!("Failed to cache user data: {}", e);
-            } else {
-                println!("Successfully cached user data for: {}", payload.username);
+            if let Some(last_updated) = stale_since {
+                // Score the stale data now and refresh both behind the response.
+                // Only the refresh caches a score, so this one is never stored
+                // as fresh or recorded in the history.
+                let (mut response, _) = score_response(state, &user, profile, window_days)?;
+                response.stale = true;
+                response.age_seconds = age_seconds(last_updated);
+                queue_refresh(state, username, &profile.name, window_days);
+                return Ok(response);
             }
-
-            (user_data, all_repos, events, pulls)
+            user
+        }
+        Ok(None) => {
+            println!("No cached user data found for: {}", username);
+            fetch_user(state, username).await?
         }
         Err(e) => {
             println!("Error checking user cache: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(GitHubError { message: format!("Database error: {}", e) })
-            ));
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)));
         }
     };
 
+    compute_score(state, username, &user, profile, window_days).await
+}
+
+fn cached_score_response(cached_score: CachedScore) -> Result<ScoreResponse, String> {
+    fn parse<T: serde::de::DeserializeOwned>(value: Value, what: &str) -> Result<T, String> {
+        serde_json::from_value(value).map_err(|e| format!("Failed to parse cached {}: {}", what, e))
+    }
+
+    Ok(ScoreResponse {
+        profile: cached_score.profile,
+        profile_version: cached_score.profile_version,
+        model_version: cached_score.model_version,
+        stale: false,
+        age_seconds: age_seconds(cached_score.last_updated),
+        last_updated: cached_score.last_updated,
+        score: parse(cached_score.score, "score")?,
+        rating: cached_score.rating,
+        stats: parse(cached_score.stats, "stats")?,
+        activity: parse(cached_score.activity, "activity")?,
+        languages: parse(cached_score.languages, "languages")?,
+    })
+}
+
+fn age_seconds(last_updated: chrono::DateTime<Utc>) -> u64 {
+    (Utc::now() - last_updated).num_seconds().max(0) as u64
+}
+
+/// Fetches fresh data from GitHub and caches it.
+async fn fetch_user(state: &AppState, username: &str) -> Result<GitHubUser, ApiError> {
+    let fetched = state.github.fetch_user(username).await
+        .map_err(|e| {
+            println!("Error fetching data for {}: {}", username, e);
+            fetch_error_response(e)
+        })?;
+
+    // Cache the user data
+    let cached_user = CachedUser {
+        username: username.to_string(),
+        user_data: fetched.profile,
+        repositories: to_values(&fetched.user.repositories),
+        events: to_values(&fetched.user.events),
+        pull_requests: to_values(&fetched.user.pull_requests),
+        last_updated: Utc::now(),
+    };
+    
+    if let Err(e) = state.db.cache_user(&cached_user).await {
+        println!("Failed to cache user data: {}", e);
+    } else {
+        println!("Successfully cached user data for: {}", username);
+    }
+
+    Ok(fetched.user)
+}
+
+/// Scores `user` with `profile`, builds the response, caches the score and
+/// records it in the history.
+async fn compute_score(
+    state: &AppState,
+    username: &str,
+    user: &GitHubUser,
+    profile: &ScoringProfile,
+    window_days: u32,
+) -> Result<ScoreResponse, ApiError> {
+    let (response, score) = score_response(state, user, profile, window_days)?;
+
+    // Cache the score
+    let cached_score = || -> Result<CachedScore, DbError> {
+        Ok(CachedScore {
+            username: username.to_string(),
+            profile: profile.name.clone(),
+            window_days,
+            profile_version: profile.version.clone(),
+            model_version: profile.model_version.clone(),
+            score: serde_json::to_value(&score)?,
+            rating: response.rating.clone(),
+            stats: serde_json::to_value(&response.stats)?,
+            activity: serde_json::to_value(&response.activity)?,
+            languages: serde_json::to_value(&response.languages)?,
+            last_updated: response.last_updated,
+        })
+    };
+    let cached = match cached_score() {
+        Ok(cached_score) => state.db.cache_score(&cached_score).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = cached {
+        println!("Failed to cache score: {}", e);
+    } else {
+        println!("Successfully cached score for: {}", username);
+    }
+
+    let history_entry = ScoreHistoryEntry {
+        username: username.to_string(),
+        profile: profile.name.clone(),
+        model_version: profile.model_version.clone(),
+        final_score: score.final_score,
+        component_scores: serde_json::to_value(&score.component_scores).unwrap_or(Value::Null),
+        computed_at: response.last_updated,
+    };
+    if let Err(e) = state.db.record_score(&history_entry).await {
+        println!("Failed to record score history for {}: {}", username, e);
+    }
+
+    Ok(response)
+}
+
+/// Scores `user` with `profile` and builds the response, without caching it.
+fn score_response(
+    state: &AppState,
+    user: &GitHubUser,
+    profile: &ScoringProfile,
+    window_days: u32,
+) -> Result<(ScoreResponse, DetailedScores), ApiError> {
+    let scorer = &profile.scorer;
+
     // Calculate repository statistics
-    let total_stars: usize = all_repos.iter()
-        .map(|repo| repo["stargazers_count"].as_u64().unwrap_or(0) as usize)
+    let total_stars: usize = user.repositories.iter()
+        .map(|repo| repo.stargazers_count as usize)
         .sum();
     
-    let total_forks: usize = all_repos.iter()
-        .map(|repo| repo["forks_count"].as_u64().unwrap_or(0) as usize)
+    let total_forks: usize = user.repositories.iter()
+        .map(|repo| repo.forks_count as usize)
         .sum();
 
-    // Calculate activity statistics
-    let commits_last_month = events.iter()
-        .filter(|e| e["type"] == "PushEvent")
-        .count();
+    // Calculate activity statistics over the requested window
+    let now = state.clock.now();
+    let window_start = now - chrono::Duration::days(window_days as i64);
+    let window_events: Vec<_> = user.events.iter()
+        .filter(|e| e.created_at >= window_start)
+        .collect();
+
+    let distinct_only = scorer.distinct_commits_only();
+    let commits = window_events.iter()
+        .map(|e| e.commit_count(distinct_only))
+        .sum();
 
-    let pull_requests_last_month = events.iter()
-        .filter(|e| e["type"] == "PullRequestEvent")
+    let pull_requests = window_events.iter()
+        .filter(|e| e.r#type == "PullRequestEvent")
         .count();
 
-    let issues_last_month = events.iter()
-        .filter(|e| e["type"] == "IssuesEvent")
+    let issues = window_events.iter()
+        .filter(|e| e.r#type == "IssuesEvent")
         .count();
 
     // Calculate language distribution
     let mut languages = HashMap::new();
-    for repo in &all_repos {
-        if let Some(lang) = repo["language"].as_str() {
-            *languages.entry(lang.to_string()).or_insert(0.0) += 1.0;
+    for repo in &user.repositories {
+        if let Some(lang) = &repo.language {
+            *languages.entry(lang.clone()).or_insert(0.0) += 1.0;
         }
     }
     
@@ -510,101 +1091,69 @@ async fn score_user(
         }
     }
 
-    // Convert the GitHub API responses to our internal types
-    println!("Converting API responses to internal types...");
-    
-    let user = GitHubUser {
-        login: payload.username.clone(),
-        repositories: all_repos.clone().into_iter()
-            .filter_map(|r| {
-                match serde_json::from_value(r.clone()) {
-                    Ok(repo) => Some(repo),
-                    Err(e) => {
-                        println!("Failed to parse repository: {}", e);
-                        None
-                    }
-                }
-            })
-            .collect(),
-        events: events.clone().into_iter()
-            .filter_map(|e| {
-                match serde_json::from_value(e.clone()) {
-                    Ok(event) => Some(event),
-                    Err(e) => {
-                        println!("Failed to parse event: {}", e);
-                        None
-                    }
-                }
-            })
-            .collect(),
-        pull_requests: pulls.into_iter()
-            .filter_map(|p| {
-                match serde_json::from_value(p.clone()) {
-                    Ok(pr) => Some(pr),
-                    Err(e) => {
-                        println!("Failed to parse pull request: {}", e);
-                        None
-                    }
-                }
-            })
-            .collect(),
-    };
-
     println!("Calculating score for user: {}", user.login);
     println!("Repositories: {}", user.repositories.len());
     println!("Events: {}", user.events.len());
     println!("Pull Requests: {}", user.pull_requests.len());
 
     // Calculate score
-    let score = state.scorer.calculate_score(&user)
+    let score = scorer.calculate_score_at(user, now)
         .map_err(|e| {
             println!("Error calculating score: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(GitHubError { message: format!("Failed to calculate score: {}", e) })
-            )
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to calculate score: {}", e))
         })?;
     
-    let rating = state.scorer.rate_score(score.final_score);
+    let rating = scorer.rate_score(score.final_score);
     println!("Score calculated successfully. Rating: {}", rating);
 
     // Prepare activity trend data (last 7 days)
     let mut activity_trend = Vec::new();
     for i in 0..7 {
-        let date = chrono::Utc::now() - chrono::Duration::days(i);
+        let date = now - chrono::Duration::days(i);
         let date_str = date.format("%Y-%m-%d").to_string();
         
-        let day_events = events.iter()
-            .filter(|e| e["created_at"].as_str().unwrap_or("").starts_with(&date_str))
+        let day_events = user.events.iter()
+            .filter(|e| e.created_at.format("%Y-%m-%d").to_string() == date_str)
             .collect::<Vec<_>>();
 
         activity_trend.push(ActivityPoint {
             date: date_str,
             commits: day_events.iter()
-                .filter(|e| e["type"] == "PushEvent")
-                .count(),
+                .map(|e| e.commit_count(distinct_only))
+                .sum(),
             pull_requests: day_events.iter()
-                .filter(|e| e["type"] == "PullRequestEvent")
+                .filter(|e| e.r#type == "PullRequestEvent")
                 .count(),
             issues: day_events.iter()
-                .filter(|e| e["type"] == "IssuesEvent")
+                .filter(|e| e.r#type == "IssuesEvent")
                 .count(),
         });
     }
 
+    let computed_at = Utc::now();
     let response = ScoreResponse {
+        profile: profile.name.clone(),
+        profile_version: profile.version.clone(),
+        model_version: profile.model_version.clone(),
+        stale: false,
+        age_seconds: 0,
+        last_updated: computed_at,
         score: score.clone(),
         rating: rating.to_string(),
         stats: UserStats {
-            total_repositories: all_repos.len(),
+            total_repositories: user.repositories.len(),
             total_stars,
             total_forks,
-            total_contributions: events.len(),
+            total_contributions: user.contributions.as_ref()
+                .map(|c| c.total() as usize)
+                .unwrap_or(user.events.len()),
         },
         activity: ActivityData {
-            commits_last_month,
-            pull_requests_last_month,
-            issues_last_month,
+            window_days,
+            events_in_window: window_events.len(),
+            commits,
+            pull_requests,
+            issues,
             activity_trend,
         },
         languages: LanguageDistribution {
@@ -612,26 +1161,1122 @@ async fn score_user(
         },
     };
 
-    // Cache the score
-    let cached_score = CachedScore {
-        username: payload.username.clone(),
-        score: serde_json::to_value(&score).unwrap(),
-        rating: rating.to_string(),
-        stats: serde_json::to_value(&response.stats).unwrap(),
-        activity: serde_json::to_value(&response.activity).unwrap(),
-        languages: serde_json::to_value(&response.languages).unwrap(),
-        last_updated: Utc::now(),
+    Ok((response, score))
+}
+
+/// Queues a background refresh of a user's data and score, unless one is
+/// already pending.
+fn queue_refresh(state: &AppState, username: &str, profile: &str, window_days: u32) {
+    let job = RefreshJob {
+        username: username.to_string(),
+        profile: profile.to_string(),
+        window_days,
     };
+    let mut pending = state.refreshing.lock().unwrap_or_else(|e| e.into_inner());
+    if !pending.insert((job.username.clone(), job.profile.clone(), window_days)) {
+        return;
+    }
+    if let Err(e) = state.refresh_tx.try_send(job) {
+        println!("Could not queue refresh for {}: {}", username, e);
+        pending.remove(&(username.to_string(), profile.to_string(), window_days));
+    }
+}
 
-    if let Err(e) = state.db.cache_score(&cached_score).await {
-        println!("Failed to cache score: {}", e);
+/// Runs queued refreshes, a few at a time, for as long as the app is alive.
+async fn run_refresh_worker(state: Weak<AppState>, mut jobs: mpsc::Receiver<RefreshJob>) {
+    let permits = Arc::new(Semaphore::new(REFRESH_CONCURRENCY));
+    while let Some(job) = jobs.recv().await {
+        let Some(state) = state.upgrade() else {
+            break;
+        };
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        tokio::spawn(async move {
+            refresh(&state, &job).await;
+            state.refreshing
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&(job.username, job.profile, job.window_days));
+            drop(permit);
+        });
+    }
+}
+
+async fn refresh(state: &AppState, job: &RefreshJob) {
+    println!("Refreshing {} for profile {}", job.username, job.profile);
+    let profile = match state.profiles.get(&job.profile) {
+        Ok(profile) => profile,
+        Err(e) => {
+            println!("Refresh of {} skipped: {}", job.username, e);
+            return;
+        }
+    };
+    let result = match fetch_user(state, &job.username).await {
+        Ok(user) => compute_score(state, &job.username, &user, &profile, job.window_days).await,
+        Err(e) => Err(e),
+    };
+    if let Err((_, Json(error))) = result {
+        println!("Refresh of {} failed: {}", job.username, error.message);
+    }
+}
+
+/// Creates a batch job scoring every user in the list in the background.
+/// Poll `GET /api/batch/{id}` for progress.
+async fn create_batch(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BatchRequest>,
+) -> Result<(StatusCode, Json<BatchResponse>), ApiError> {
+    let usernames = distinct_usernames(&payload.usernames);
+    if usernames.is_empty() || usernames.len() > MAX_BATCH_USERS {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("usernames must name between 1 and {} distinct users", MAX_BATCH_USERS),
+        ));
+    }
+    for username in &usernames {
+        validate_username(username)?;
+    }
+    let (profile, window_days) = score_options(&state, payload.profile.as_deref(), payload.window_days)?;
+
+    let job = BatchJob {
+        id: new_batch_id(),
+        profile: profile.name.clone(),
+        window_days,
+        created_at: Utc::now(),
+    };
+    state.db.create_batch(&job, &usernames).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create batch job: {}", e)))?;
+    println!("Created batch job {} for {} users", job.id, usernames.len());
+    state.batch_wake.notify_one();
+
+    let response = batch_response(&state, job).await?;
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+/// Progress of a batch job, with the results so far.
+async fn batch_status(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    let job = find_batch(&state, &id).await?;
+    batch_response(&state, job).await.map(Json)
+}
+
+/// A batch job's results as CSV, one row per user in submission order.
+/// Users not scored yet are listed as `pending`.
+async fn batch_results_csv(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let job = find_batch(&state, &id).await?;
+    let items = batch_items(&state, &job.id).await?;
+
+    let components: BTreeSet<String> = items.iter()
+        .filter_map(|item| item.component_scores.as_ref()?.as_object())
+        .flat_map(|scores| scores.keys().cloned())
+        .collect();
+    let mut header = vec!["username", "status", "final_score", "rating"];
+    header.extend(components.iter().map(String::as_str));
+    header.push("error");
+
+    let mut csv = csv_row(header.into_iter().map(str::to_string));
+    for item in &items {
+        let mut row = vec![
+            item.username.clone(),
+            item.status.as_str().to_string(),
+            item.final_score.map(|s| s.to_string()).unwrap_or_default(),
+            item.rating.clone().unwrap_or_default(),
+        ];
+        row.extend(components.iter().map(|name| {
+            item.component_scores.as_ref()
+                .and_then(|scores| scores[name].as_f64())
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+        }));
+        row.push(item.error.clone().unwrap_or_default());
+        csv.push_str(&csv_row(row));
+    }
+
+    let disposition = format!("attachment; filename=\"batch-{}.csv\"", job.id);
+    Ok((
+        [(CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (CONTENT_DISPOSITION, disposition)],
+        csv,
+    ).into_response())
+}
+
+async fn find_batch(state: &AppState, id: &str) -> Result<BatchJob, ApiError> {
+    match state.db.get_batch(id).await {
+        Ok(Some(job)) => Ok(job),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("No batch job with id {}", id))),
+        Err(e) => Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read batch job: {}", e))),
+    }
+}
+
+async fn batch_items(state: &AppState, job_id: &str) -> Result<Vec<BatchItem>, ApiError> {
+    state.db.batch_items(job_id).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read batch results: {}", e)))
+}
+
+async fn batch_response(state: &AppState, job: BatchJob) -> Result<BatchResponse, ApiError> {
+    let items = batch_items(state, &job.id).await?;
+    let count = |status: BatchItemStatus| items.iter().filter(|item| item.status == status).count();
+    let (done, failed) = (count(BatchItemStatus::Done), count(BatchItemStatus::Failed));
+    let status = if done + failed == items.len() {
+        BatchState::Completed
+    } else if done + failed == 0 && count(BatchItemStatus::Running) == 0 {
+        BatchState::Queued
     } else {
-        println!("Successfully cached score for: {}", payload.username);
+        BatchState::Running
+    };
+
+    Ok(BatchResponse {
+        id: job.id,
+        profile: job.profile,
+        window_days: job.window_days,
+        created_at: job.created_at,
+        status,
+        total: items.len(),
+        done,
+        failed,
+        results: items.into_iter()
+            .map(|item| BatchResult {
+                username: item.username,
+                status: item.status,
+                final_score: item.final_score,
+                rating: item.rating,
+                component_scores: item.component_scores,
+                error: item.error,
+            })
+            .collect(),
+    })
+}
+
+/// A random (v4 UUID) job id, so jobs can't be found by guessing.
+fn new_batch_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// One CSV line, quoting fields that need it.
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields.into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
+
+/// Scores pending batch items a few at a time for as long as the app is
+/// alive. Items are read back from the database, so jobs cut short by a
+/// restart carry on where they stopped. Work pauses while GitHub's rate
+/// budget is down to the reserve kept for interactive requests.
+async fn run_batch_worker(state: Weak<AppState>) {
+    let permits = Arc::new(Semaphore::new(BATCH_CONCURRENCY));
+    loop {
+        let Some(state) = state.upgrade() else {
+            break;
+        };
+        if let Some(wait) = state.github.rate_limit_wait(BATCH_RATE_RESERVE_PERCENT, Utc::now()) {
+            println!("Batch jobs paused for {}s to leave GitHub rate budget for requests", wait);
+            drop(state);
+            tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+            continue;
+        }
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+
+        match state.db.claim_batch_item(chrono::Duration::minutes(BATCH_LEASE_MINUTES)).await {
+            Ok(Some(item)) => {
+                tokio::spawn(async move {
+                    score_batch_item(&state, item).await;
+                    drop(permit);
+                    state.batch_wake.notify_one();
+                });
+            }
+            Ok(None) => {
+                drop(permit);
+                let _ = tokio::time::timeout(BATCH_POLL_INTERVAL, state.batch_wake.notified()).await;
+            }
+            Err(e) => {
+                println!("Failed to read pending batch items: {}", e);
+                drop(permit);
+                drop(state);
+                tokio::time::sleep(BATCH_POLL_INTERVAL).await;
+            }
+        }
     }
+}
+
+/// Scores one batch item and stores the result. Rate limited items go back
+/// to pending and are tried again once the limit resets, as are items whose
+/// job couldn't be read. Server and network errors are tried again a few
+/// times; other errors, such as an unknown user, fail the item.
+async fn score_batch_item(state: &Arc<AppState>, mut item: BatchItem) {
+    // Results are only stored while the claim this worker took still holds
+    let Some(claimed_at) = item.claimed_at else {
+        println!("Batch item {} was handed out without a claim", item.username);
+        return;
+    };
+    let result = match state.db.get_batch(&item.job_id).await {
+        Ok(Some(job)) => match state.profiles.get(&job.profile) {
+            Ok(profile) => get_score(state, &item.username, &profile, job.window_days).await,
+            Err(e) => Err(api_error(StatusCode::BAD_REQUEST, e.to_string())),
+        },
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("No batch job with id {}", item.job_id))),
+        Err(e) => {
+            println!("Failed to read batch job {}: {}", item.job_id, e);
+            retry_batch_item(state, item, claimed_at, chrono::Duration::seconds(BATCH_RETRY_DELAY_SECS)).await;
+            return;
+        }
+    };
 
-    Ok(Json(response))
+    match result {
+        Ok(response) => {
+            item.status = BatchItemStatus::Done;
+            item.final_score = Some(response.score.final_score);
+            item.rating = Some(response.rating);
+            item.component_scores = serde_json::to_value(&response.score.component_scores).ok();
+            item.error = None;
+        }
+        Err((StatusCode::TOO_MANY_REQUESTS, Json(error))) => {
+            let wait = error.retry_after.unwrap_or(60);
+            println!("Batch item {} rate limited, retrying in {}s", item.username, wait);
+            retry_batch_item(state, item, claimed_at, chrono::Duration::seconds(wait as i64)).await;
+            return;
+        }
+        Err((status, Json(error))) => match batch_retry_delay(status, item.attempts) {
+            Some(delay) => {
+                println!(
+                    "Batch item {} failed ({}), retrying in {}s",
+                    item.username, error.message, delay.num_seconds()
+                );
+                item.attempts += 1;
+                item.error = Some(error.message);
+                retry_batch_item(state, item, claimed_at, delay).await;
+                return;
+            }
+            None => {
+                item.status = BatchItemStatus::Failed;
+                item.error = Some(error.message);
+            }
+        },
+    }
+    item.claimed_at = None;
+    item.next_attempt_at = None;
+    item.updated_at = Utc::now();
+    store_batch_item(state, &item, claimed_at).await;
+}
+
+/// How long a batch item that failed with `status` after `attempts` earlier
+/// failed tries waits before the next, or `None` if the failure is final.
+fn batch_retry_delay(status: StatusCode, attempts: u32) -> Option<chrono::Duration> {
+    (status.is_server_error() && attempts + 1 < BATCH_MAX_ATTEMPTS)
+        .then(|| chrono::Duration::seconds(BATCH_RETRY_DELAY_SECS << attempts))
+}
+
+/// Hands a batch item back as pending, not to be claimed again for `delay`.
+/// If this can't be stored the item is reclaimed when its lease runs out.
+async fn retry_batch_item(state: &AppState, mut item: BatchItem, claimed_at: DateTime<Utc>, delay: chrono::Duration) {
+    let now = Utc::now();
+    item.status = BatchItemStatus::Pending;
+    item.claimed_at = None;
+    item.next_attempt_at = Some(now + delay);
+    item.updated_at = now;
+    store_batch_item(state, &item, claimed_at).await;
 }
 
-async fn health_check() -> &'static str {
-    "OK"
-} 
\ No newline at end of file
+/// Stores `item` under the claim its worker took at `claimed_at`. If the lease
+/// ran out and another worker has the item now, its outcome is dropped.
+async fn store_batch_item(state: &AppState, item: &BatchItem, claimed_at: DateTime<Utc>) {
+    match state.db.update_batch_item(item, claimed_at).await {
+        Ok(true) => {}
+        Ok(false) => println!("Batch item {} was claimed by another worker, dropping this attempt", item.username),
+        Err(e) => println!("Failed to store batch item {}: {}", item.username, e),
+    }
+}
+
+/// Returns the scores computed for a user over time, oldest first.
+async fn score_history(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<HistoryResponse>, ApiError> {
+    let bad_request = |message: String| api_error(StatusCode::BAD_REQUEST, message);
+
+    let from = query.from.as_deref()
+        .map(|v| parse_time_bound(v, false))
+        .transpose()
+        .map_err(bad_request)?;
+    let to = query.to.as_deref()
+        .map(|v| parse_time_bound(v, true))
+        .transpose()
+        .map_err(bad_request)?
+        .unwrap_or_else(Utc::now);
+    if from.is_some_and(|from| from > to) {
+        return Err(bad_request("from must not be after to".to_string()));
+    }
+    let interval = match query.interval.as_deref() {
+        Some(v) => v.parse::<HistoryInterval>().map_err(bad_request)?,
+        None => HistoryInterval::default(),
+    };
+    // History outlives profiles, so any profile name is accepted here
+    let profile = query.profile.unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+    let entries = state.db
+        .score_history(&username, &profile, from.unwrap_or(DateTime::UNIX_EPOCH), to)
+        .await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read score history: {}", e)))?;
+    let points: Vec<HistoryPoint> = entries.into_iter()
+        .map(|entry| HistoryPoint {
+            at: entry.computed_at,
+            final_score: entry.final_score,
+            component_scores: serde_json::from_value(entry.component_scores).unwrap_or_default(),
+            model_version: entry.model_version,
+            samples: 1,
+        })
+        .collect();
+
+    Ok(Json(HistoryResponse {
+        username,
+        profile,
+        interval,
+        from,
+        to,
+        points: downsample(&points, interval),
+    }))
+}
+
+/// Parses an RFC 3339 timestamp or a `YYYY-MM-DD` date, which stands for the
+/// start of that day, or its end when `end_of_day` is set.
+fn parse_time_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD) or an RFC 3339 timestamp", value))?;
+    let time = if end_of_day {
+        chrono::NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap_or(chrono::NaiveTime::MIN)
+    } else {
+        chrono::NaiveTime::MIN
+    };
+    Ok(date.and_time(time).and_utc())
+}
+
+async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        corrupt_rows: state.db.corrupt_rows(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path as UrlPath, Query};
+    use github_score_api::scoring::FixedClock;
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+
+    /// Recorded GitHub responses, one directory per user archetype.
+    const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/github");
+    /// Expected `ScoreResponse` for each fixture user.
+    const SNAPSHOTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+    /// The time the fixtures were recorded at.
+    const RECORDED_AT: &str = "2025-06-01T00:00:00Z";
+
+    fn fixture(login: &str, name: &str) -> Option<Value> {
+        let path = Path::new(FIXTURES_DIR).join(login).join(format!("{}.json", name));
+        let contents = std::fs::read_to_string(path).ok()?;
+        Some(serde_json::from_str(&contents).expect("fixture is valid JSON"))
+    }
+
+    fn fixture_response(login: &str, name: &str) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        fixture(login, name)
+            .map(Json)
+            .ok_or((StatusCode::NOT_FOUND, Json(json!({ "message": "Not Found" }))))
+    }
+
+    /// Serves the fixtures the way the GitHub REST API would.
+    async fn spawn_mock_github() -> String {
+        let router = Router::new()
+            .route("/users/{login}", get(|UrlPath(login): UrlPath<String>| async move {
+                fixture_response(&login, "user")
+            }))
+            .route("/users/{login}/repos", get(|UrlPath(login): UrlPath<String>| async move {
+                fixture_response(&login, "repos")
+            }))
+            .route("/users/{login}/events", get(|UrlPath(login): UrlPath<String>| async move {
+                fixture_response(&login, "events")
+            }))
+            .route("/search/issues", get(|Query(params): Query<HashMap<String, String>>| async move {
+                let login = params.get("q")
+                    .and_then(|q| q.split_whitespace().find_map(|term| term.strip_prefix("author:")))
+                    .unwrap_or_default();
+                let items = match params.get("page").map(String::as_str) {
+                    Some("1") | None => fixture(login, "pulls").unwrap_or_else(|| json!([])),
+                    _ => json!([]),
+                };
+                let total = items.as_array().map_or(0, Vec::len);
+                Json(json!({ "total_count": total, "incomplete_results": false, "items": items }))
+            }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    async fn test_state() -> Arc<AppState> {
+        test_state_with_ttl(CacheTtl::default()).await
+    }
+
+    async fn test_state_with_ttl(ttl: CacheTtl) -> Arc<AppState> {
+        test_state_with_db(Arc::new(Database::in_memory().await.unwrap().with_ttl(ttl))).await
+    }
+
+    async fn test_state_with_db(db: Arc<Database>) -> Arc<AppState> {
+        let base_url = spawn_mock_github().await;
+        let recorded_at = RECORDED_AT.parse().unwrap();
+        AppState::new(
+            Arc::new(ScoringProfiles::single(ScoringConfig::default()).unwrap()),
+            Arc::new(GitHubClient::new(None).unwrap().with_base_url(base_url)),
+            db,
+            Arc::new(FixedClock(recorded_at)),
+        )
+    }
+
+    async fn score(state: &Arc<AppState>, username: &str) -> Value {
+        let request = ScoreRequest {
+            username: username.to_string(),
+            window_days: None,
+            profile: None,
+        };
+        match score_user(State(state.clone()), Json(request)).await {
+            Ok(Json(response)) => serde_json::to_value(response).unwrap(),
+            Err((status, Json(error))) => panic!("scoring {} failed with {}: {}", username, status, error.message),
+        }
+    }
+
+    /// Returns the path of the first difference, allowing for float rounding.
+    fn first_difference(expected: &Value, actual: &Value, path: &str) -> Option<String> {
+        match (expected, actual) {
+            (Value::Number(a), Value::Number(b)) => {
+                let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                ((a - b).abs() > 1e-9).then(|| format!("{}: expected {}, got {}", path, a, b))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                keys.into_iter().find_map(|key| {
+                    let path = format!("{}.{}", path, key);
+                    match (a.get(key), b.get(key)) {
+                        (Some(a), Some(b)) => first_difference(a, b, &path),
+                        (Some(_), None) => Some(format!("{}: missing", path)),
+                        (None, _) => Some(format!("{}: unexpected", path)),
+                    }
+                })
+            }
+            (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a.iter()
+                .zip(b)
+                .enumerate()
+                .find_map(|(i, (a, b))| first_difference(a, b, &format!("{}[{}]", path, i))),
+            (a, b) => (a != b).then(|| format!("{}: expected {}, got {}", path, a, b)),
+        }
+    }
+
+    fn fixture_users() -> Vec<String> {
+        let mut users: Vec<String> = std::fs::read_dir(FIXTURES_DIR)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        users.sort();
+        users
+    }
+
+    /// Scores every fixture user and compares the response with its snapshot.
+    /// Run with `UPDATE_SNAPSHOTS=1` to accept model changes, then review the
+    /// diff in `tests/snapshots`.
+    #[tokio::test]
+    async fn test_fixture_users_match_snapshots() {
+        let update = env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+        let state = test_state().await;
+        let mut failures = Vec::new();
+
+        for username in fixture_users() {
+            let actual = score(&state, &username).await;
+            // The second request is served from the score cache
+            let mut cached = score(&state, &username).await;
+            cached["age_seconds"] = actual["age_seconds"].clone();
+            assert!(first_difference(&actual, &cached, "").is_none());
+
+            let snapshot: PathBuf = Path::new(SNAPSHOTS_DIR).join(format!("{}.json", username));
+            if update {
+                std::fs::create_dir_all(SNAPSHOTS_DIR).unwrap();
+                std::fs::write(&snapshot, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+                continue;
+            }
+
+            let expected: Value = match std::fs::read_to_string(&snapshot) {
+                Ok(contents) => serde_json::from_str(&contents).unwrap(),
+                Err(_) => {
+                    failures.push(format!("{}: no snapshot at {}", username, snapshot.display()));
+                    continue;
+                }
+            };
+            if let Some(difference) = first_difference(&expected, &actual, &username) {
+                failures.push(difference);
+            }
+        }
+
+        assert!(
+            failures.is_empty(),
+            "responses differ from snapshots (rerun with UPDATE_SNAPSHOTS=1 to accept):\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_is_not_found() {
+        let state = test_state().await;
+        let request = ScoreRequest {
+            username: "nobody-here".to_string(),
+            window_days: None,
+            profile: None,
+        };
+        let Err((status, _)) = score_user(State(state), Json(request)).await else {
+            panic!("expected an error for a missing user");
+        };
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    async fn score_by_path(
+        state: &Arc<AppState>,
+        username: &str,
+        query: ScoreQuery,
+        if_none_match: Option<&str>,
+    ) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = if_none_match {
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(etag).unwrap());
+        }
+        match score_user_by_path(State(state.clone()), UrlPath(username.to_string()), Query(query), headers).await {
+            Ok(response) => response,
+            Err((status, Json(error))) => panic!("scoring {} failed with {}: {}", username, status, error.message),
+        }
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_score_sets_cache_headers() {
+        let state = test_state().await;
+        let posted = score(&state, "fresh-start").await;
+
+        let response = score_by_path(&state, "fresh-start", ScoreQuery::default(), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+        let cache_control = response.headers()[CACHE_CONTROL].to_str().unwrap();
+        assert!(cache_control.starts_with("public, max-age="), "{}", cache_control);
+        let mut got = body_json(response).await;
+        got["age_seconds"] = posted["age_seconds"].clone();
+        assert!(first_difference(&posted, &got, "").is_none());
+
+        let response = score_by_path(&state, "fresh-start", ScoreQuery::default(), Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+
+        // A refresh of a score this young is ignored
+        let refresh = ScoreQuery { refresh: true, ..ScoreQuery::default() };
+        let response = score_by_path(&state, "fresh-start", refresh, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // Past that, a refresh computes a new score, so the old tag no longer matches
+        let window = DEFAULT_ACTIVITY_WINDOW_DAYS;
+        let mut cached = state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, window).await.unwrap().unwrap();
+        cached.last_updated -= chrono::Duration::seconds(MIN_REFRESH_AGE_SECS as i64 + 60);
+        state.db.cache_score(&cached).await.unwrap();
+        let refresh = ScoreQuery { refresh: true, ..ScoreQuery::default() };
+        let response = score_by_path(&state, "fresh-start", refresh, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[ETAG], etag.as_str());
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+
+        let query = ScoreQuery { window: Some(7), ..ScoreQuery::default() };
+        let weekly = body_json(score_by_path(&state, "fresh-start", query, None).await).await;
+        assert_eq!(weekly["activity"]["window_days"], 7);
+        // Each window has its own cache entry, so the default one is still there
+        let response = score_by_path(&state, "fresh-start", ScoreQuery::default(), Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let query = ScoreQuery { profile: Some("nope".to_string()), ..ScoreQuery::default() };
+        let result =
+            score_user_by_path(State(state.clone()), UrlPath("fresh-start".to_string()), Query(query), HeaderMap::new()).await;
+        let Err((status, _)) = result else {
+            panic!("expected an error for an unknown profile");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Only GitHub logins reach GitHub paths
+        for username in ["", "../orgs/rust-lang", "fresh start", "a".repeat(40).as_str()] {
+            let result = score_user_by_path(
+                State(state.clone()),
+                UrlPath(username.to_string()),
+                Query(ScoreQuery::default()),
+                HeaderMap::new(),
+            )
+            .await;
+            assert_eq!(result.err().map(|(status, _)| status), Some(StatusCode::BAD_REQUEST), "{:?}", username);
+        }
+    }
+
+    async fn compare_request(state: &Arc<AppState>, usernames: &[&str]) -> Result<Value, (StatusCode, String)> {
+        let request = CompareRequest {
+            usernames: usernames.iter().map(|u| u.to_string()).collect(),
+            window_days: None,
+            profile: None,
+        };
+        compare_users(State(state.clone()), Json(request))
+            .await
+            .map(|Json(response)| serde_json::to_value(response).unwrap())
+            .map_err(|(status, Json(error))| (status, error.message))
+    }
+
+    #[tokio::test]
+    async fn test_compare_users() {
+        let state = test_state().await;
+        let maintainer = score(&state, "core-maintainer").await;
+        let newcomer = score(&state, "fresh-start").await;
+
+        let compared = compare_request(&state, &["fresh-start", "core-maintainer", "Fresh-Start"]).await.unwrap();
+        let ranking = compared["ranking"].as_array().unwrap();
+        assert_eq!(ranking.len(), 2);
+        let final_score = |response: &Value| response["score"]["final_score"].as_f64().unwrap();
+        let (first, second) = if final_score(&maintainer) > final_score(&newcomer) {
+            ("core-maintainer", "fresh-start")
+        } else {
+            ("fresh-start", "core-maintainer")
+        };
+        assert_eq!(ranking[0]["username"], first);
+        assert_eq!(ranking[0]["behind_leader"], 0.0);
+        assert_eq!(ranking[1]["username"], second);
+        assert_eq!(ranking[1]["rank"], 2);
+
+        let code_quality = &compared["components"]["code_quality"];
+        let deltas = code_quality["deltas"].as_object().unwrap();
+        assert!(deltas.values().map(|d| d.as_f64().unwrap()).sum::<f64>().abs() < 1e-9);
+        assert_eq!(code_quality["scores"]["fresh-start"], newcomer["score"]["component_scores"]["code_quality"]);
+        assert_eq!(compared["scores"]["core-maintainer"]["rating"], maintainer["rating"]);
+
+        let (status, _) = compare_request(&state, &["fresh-start", "fresh-start"]).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let too_many: Vec<String> = (0..=MAX_COMPARE_USERS).map(|i| format!("user-{}", i)).collect();
+        let too_many: Vec<&str> = too_many.iter().map(String::as_str).collect();
+        assert_eq!(compare_request(&state, &too_many).await.unwrap_err().0, StatusCode::BAD_REQUEST);
+        let invalid = compare_request(&state, &["fresh-start", "users/fresh-start"]).await.unwrap_err();
+        assert_eq!(invalid.0, StatusCode::BAD_REQUEST);
+        let (status, message) = compare_request(&state, &["fresh-start", "nobody-here"]).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(message.starts_with("nobody-here: "), "{}", message);
+    }
+
+    async fn batch(state: &Arc<AppState>, id: &str) -> Result<Value, StatusCode> {
+        batch_status(State(state.clone()), UrlPath(id.to_string()))
+            .await
+            .map(|Json(response)| serde_json::to_value(response).unwrap())
+            .map_err(|(status, _)| status)
+    }
+
+    /// Polls a batch job until every user in it has been processed.
+    async fn wait_for_batch(state: &Arc<AppState>, id: &str) -> Value {
+        let completed = async {
+            loop {
+                let job = batch(state, id).await.unwrap();
+                if job["status"] == "completed" {
+                    return job;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), completed).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_job() {
+        let state = test_state().await;
+        let request = BatchRequest {
+            usernames: ["fresh-start", "nobody-here", "core-maintainer", "Fresh-Start", " "]
+                .map(String::from)
+                .to_vec(),
+            window_days: None,
+            profile: None,
+        };
+        let (status, Json(created)) = create_batch(State(state.clone()), Json(request)).await.unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(created.total, 3);
+
+        let job = wait_for_batch(&state, &created.id).await;
+        assert_eq!((job["done"].as_u64(), job["failed"].as_u64()), (Some(2), Some(1)));
+        let results = job["results"].as_array().unwrap();
+        assert_eq!(results[0]["final_score"], score(&state, "fresh-start").await["score"]["final_score"]);
+        assert_eq!(results[1]["status"], "failed");
+        assert!(results[1]["error"].as_str().unwrap().contains("Not Found"));
+        assert_eq!(results[2]["username"], "core-maintainer");
+
+        let csv = batch_results_csv(State(state.clone()), UrlPath(created.id.clone())).await.unwrap();
+        assert_eq!(csv.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+        let bytes = axum::body::to_bytes(csv.into_body(), usize::MAX).await.unwrap();
+        let csv = String::from_utf8(bytes.to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("username,status,final_score,rating,code_quality,"), "{}", lines[0]);
+        assert!(lines[0].ends_with(",error"));
+        assert!(lines[1].starts_with("fresh-start,done,0."), "{}", lines[1]);
+        assert!(lines[2].starts_with("nobody-here,failed,,,"), "{}", lines[2]);
+
+        let empty = BatchRequest { usernames: vec![" ".to_string()], window_days: None, profile: None };
+        let Err((status, _)) = create_batch(State(state.clone()), Json(empty)).await else {
+            panic!("expected an error for an empty batch");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(batch(&state, "no-such-job").await, Err(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_batch_item_is_retried_after_server_errors() {
+        let offline = AppState::new(
+            Arc::new(ScoringProfiles::single(ScoringConfig::default()).unwrap()),
+            Arc::new(GitHubClient::new(None).unwrap().with_base_url("http://127.0.0.1:1".to_string())),
+            Arc::new(Database::in_memory().await.unwrap()),
+            Arc::new(SystemClock),
+        );
+        let request = BatchRequest { usernames: vec!["fresh-start".to_string()], window_days: None, profile: None };
+        let (_, Json(created)) = create_batch(State(offline.clone()), Json(request)).await.unwrap();
+
+        // GitHub can't be reached, so the item goes back to wait for another try
+        let retried = async {
+            loop {
+                let items = offline.db.batch_items(&created.id).await.unwrap();
+                if items[0].attempts > 0 {
+                    return items[0].clone();
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        };
+        let item = tokio::time::timeout(std::time::Duration::from_secs(5), retried).await.unwrap();
+        assert_eq!((item.status, item.attempts), (BatchItemStatus::Pending, 1));
+        assert!(item.error.is_some());
+        assert!(item.next_attempt_at.unwrap() > Utc::now() + chrono::Duration::seconds(BATCH_RETRY_DELAY_SECS - 10));
+
+        let delays: Vec<Option<i64>> = (0..BATCH_MAX_ATTEMPTS)
+            .map(|attempts| batch_retry_delay(StatusCode::BAD_GATEWAY, attempts).map(|d| d.num_seconds()))
+            .collect();
+        assert_eq!(delays, vec![Some(60), Some(120), Some(240), None]);
+        assert_eq!(batch_retry_delay(StatusCode::INTERNAL_SERVER_ERROR, 0), Some(chrono::Duration::seconds(60)));
+        assert_eq!(batch_retry_delay(StatusCode::NOT_FOUND, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_job_resumes_after_restart() {
+        // A job the previous process never got to
+        let db = Arc::new(Database::in_memory().await.unwrap());
+        let job = BatchJob {
+            id: "left-over".to_string(),
+            profile: DEFAULT_PROFILE.to_string(),
+            window_days: DEFAULT_ACTIVITY_WINDOW_DAYS,
+            created_at: Utc::now(),
+        };
+        db.create_batch(&job, &["fresh-start".to_string()]).await.unwrap();
+
+        let state = test_state_with_db(db).await;
+        let job = wait_for_batch(&state, "left-over").await;
+        assert_eq!(job["done"], 1);
+        assert_eq!(job["results"][0]["rating"], score(&state, "fresh-start").await["rating"]);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_corrupt_rows() {
+        let dir = env::temp_dir().join(format!("github-cache-health-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cache.db");
+        let state = test_state_with_db(Arc::new(Database::open(&path).await.unwrap())).await;
+        let Json(health) = health_check(State(state.clone())).await;
+        assert_eq!((health.status, health.corrupt_rows), ("ok", 0));
+
+        let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", path.display())).await.unwrap();
+        sqlx::query("INSERT INTO cached_users VALUES ('fresh-start', '{\"login\":', '[]', '[]', '[]', ?)")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        // The unreadable row is dropped and the user fetched again
+        score(&state, "fresh-start").await;
+        let Json(health) = health_check(State(state)).await;
+        assert_eq!(health.corrupt_rows, 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_model_change_invalidates_cached_score() {
+        let state = test_state().await;
+        let before = score(&state, "fresh-start").await;
+
+        let retuned = ScoringConfig {
+            distinct_commits_only: true,
+            ..ScoringConfig::default()
+        };
+        let state = AppState::new(
+            Arc::new(ScoringProfiles::single(retuned).unwrap()),
+            state.github.clone(),
+            state.db.clone(),
+            state.clock.clone(),
+        );
+        let after = score(&state, "fresh-start").await;
+
+        let current = state.profiles.default_profile().model_version.clone();
+        assert_ne!(before["model_version"], after["model_version"]);
+        assert_eq!(after["model_version"], current);
+        let cached = state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, DEFAULT_ACTIVITY_WINDOW_DAYS).await.unwrap().unwrap();
+        assert_eq!(cached.model_version, current);
+    }
+
+    #[tokio::test]
+    async fn test_stale_score_is_served_and_refreshed() {
+        let state = test_state_with_ttl(CacheTtl {
+            user_data: chrono::Duration::hours(1),
+            scores: chrono::Duration::hours(1),
+            stale_while_revalidate: chrono::Duration::days(1),
+        })
+        .await;
+        let fresh = score(&state, "fresh-start").await;
+        assert_eq!(fresh["stale"], false);
+
+        // Age both cache entries past their TTL
+        let two_hours_ago = Utc::now() - chrono::Duration::hours(2);
+        let mut cached_user = state.db.get_cached_user("fresh-start").await.unwrap().unwrap();
+        cached_user.last_updated = two_hours_ago;
+        state.db.cache_user(&cached_user).await.unwrap();
+        let mut cached_score = state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, DEFAULT_ACTIVITY_WINDOW_DAYS).await.unwrap().unwrap();
+        cached_score.last_updated = two_hours_ago;
+        state.db.cache_score(&cached_score).await.unwrap();
+
+        let stale = score(&state, "fresh-start").await;
+        assert_eq!(stale["stale"], true);
+        assert!(stale["age_seconds"].as_u64().unwrap() >= 7200);
+        assert!(first_difference(&fresh["score"], &stale["score"], "score").is_none());
+
+        // The queued refresh rewrites the cache in the background
+        let refreshed = async {
+            loop {
+                let cached = state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, DEFAULT_ACTIVITY_WINDOW_DAYS).await.unwrap().unwrap();
+                if cached.last_updated > two_hours_ago + chrono::Duration::hours(1) {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), refreshed).await.unwrap();
+        assert_eq!(score(&state, "fresh-start").await["stale"], false);
+    }
+
+    #[tokio::test]
+    async fn test_score_of_stale_user_data_is_not_cached() {
+        let state = test_state_with_ttl(CacheTtl {
+            user_data: chrono::Duration::hours(1),
+            scores: chrono::Duration::hours(1),
+            stale_while_revalidate: chrono::Duration::days(1),
+        })
+        .await;
+        score(&state, "fresh-start").await;
+
+        // Stale user data and no usable score, with GitHub out of reach
+        let mut cached_user = state.db.get_cached_user("fresh-start").await.unwrap().unwrap();
+        cached_user.last_updated = Utc::now() - chrono::Duration::hours(2);
+        state.db.cache_user(&cached_user).await.unwrap();
+        let window = DEFAULT_ACTIVITY_WINDOW_DAYS;
+        let mut cached_score = state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, window).await.unwrap().unwrap();
+        cached_score.last_updated = Utc::now() - chrono::Duration::days(3);
+        state.db.cache_score(&cached_score).await.unwrap();
+        let offline = AppState::new(
+            Arc::new(ScoringProfiles::single(ScoringConfig::default()).unwrap()),
+            Arc::new(GitHubClient::new(None).unwrap().with_base_url("http://127.0.0.1:1".to_string())),
+            state.db.clone(),
+            state.clock.clone(),
+        );
+
+        let stale = score(&offline, "fresh-start").await;
+        assert_eq!(stale["stale"], true);
+        assert!(stale["age_seconds"].as_u64().unwrap() >= 7200);
+
+        // The refresh fails, and nothing was stored in its place
+        let refresh_done = async {
+            while !offline.refreshing.lock().unwrap().is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), refresh_done).await.unwrap();
+        assert!(state.db.get_cached_score("fresh-start", DEFAULT_PROFILE, window).await.unwrap().is_none());
+        let series = history(&state, "fresh-start", &[]).await.unwrap();
+        assert_eq!(series["points"].as_array().unwrap().len(), 1);
+        assert_eq!(score(&offline, "fresh-start").await["stale"], true);
+    }
+
+    async fn history(state: &Arc<AppState>, username: &str, query: &[(&str, &str)]) -> Result<Value, StatusCode> {
+        let query = serde_json::from_value(json!(query.iter().cloned().collect::<HashMap<_, _>>())).unwrap();
+        score_history(State(state.clone()), UrlPath(username.to_string()), Query(query))
+            .await
+            .map(|Json(response)| serde_json::to_value(response).unwrap())
+            .map_err(|(status, _)| status)
+    }
+
+    #[tokio::test]
+    async fn test_score_history_series() {
+        let state = test_state().await;
+        let scored = score(&state, "fresh-start").await;
+
+        let series = history(&state, "fresh-start", &[]).await.unwrap();
+        assert_eq!(series["points"].as_array().unwrap().len(), 1);
+        assert_eq!(series["points"][0]["final_score"], scored["score"]["final_score"]);
+        assert_eq!(series["points"][0]["model_version"], scored["model_version"]);
+        assert!(series["points"][0]["component_scores"]["code_quality"].is_number());
+
+        // Earlier computations, two in one week and one in the week before
+        for (at, final_score) in [("2025-05-05T09:00:00Z", 10.0), ("2025-05-14T09:00:00Z", 20.0), ("2025-05-15T09:00:00Z", 30.0)] {
+            let entry = ScoreHistoryEntry {
+                username: "fresh-start".to_string(),
+                profile: DEFAULT_PROFILE.to_string(),
+                model_version: "0-old".to_string(),
+                final_score,
+                component_scores: json!({ "code_quality": final_score / 100.0 }),
+                computed_at: at.parse().unwrap(),
+            };
+            state.db.record_score(&entry).await.unwrap();
+        }
+
+        let may = history(&state, "fresh-start", &[("from", "2025-05-10"), ("to", "2025-05-31")]).await.unwrap();
+        let totals: Vec<f64> = may["points"].as_array().unwrap().iter()
+            .map(|p| p["final_score"].as_f64().unwrap())
+            .collect();
+        assert_eq!(totals, vec![20.0, 30.0]);
+
+        let weekly = history(&state, "fresh-start", &[("to", "2025-05-31"), ("interval", "weekly")]).await.unwrap();
+        let points = weekly["points"].as_array().unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1]["at"], "2025-05-12T00:00:00Z");
+        assert_eq!(points[1]["final_score"], 25.0);
+        assert_eq!(points[1]["samples"], 2);
+
+        let monthly = history(&state, "fresh-start", &[("to", "2025-05-31"), ("interval", "monthly")]).await.unwrap();
+        assert_eq!(monthly["points"].as_array().unwrap().len(), 1);
+        assert_eq!(monthly["points"][0]["samples"], 3);
+
+        assert!(history(&state, "nobody", &[]).await.unwrap()["points"].as_array().unwrap().is_empty());
+        assert_eq!(history(&state, "fresh-start", &[("interval", "daily")]).await, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(history(&state, "fresh-start", &[("from", "May")]).await, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            history(&state, "fresh-start", &[("from", "2025-06-01"), ("to", "2025-05-01")]).await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    /// Looks variables up in `pairs` instead of the environment.
+    fn vars(pairs: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name: &str| pairs.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+    }
+
+    #[test]
+    fn test_scoring_overrides_are_validated() {
+        let config = apply_scoring_overrides(
+            ScoringConfig::default(),
+            vars(&[("DISTINCT_COMMITS_ONLY", "TRUE"), ("DECAY_FUNCTION", "linear"), ("DECAY_FLOOR", "0.5")]),
+        )
+        .unwrap();
+        assert!(config.distinct_commits_only);
+        assert_eq!(config.time_decay.floor, 0.5);
+
+        for invalid in [
+            &[("DISTINCT_COMMITS_ONLY", "yes")][..],
+            &[("DECAY_FUNCTION", "cubic")],
+            &[("DECAY_HALF_LIFE_DAYS", "a year")],
+            &[("DECAY_HALF_LIFE_DAYS", "0")],
+            &[("DECAY_FLOOR", "1.5")],
+        ] {
+            assert!(apply_scoring_overrides(ScoringConfig::default(), vars(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_cache_ttls_are_validated() {
+        let ttl = cache_ttl_from_env(vars(&[("CACHE_SCORE_TTL_SECS", "60")])).unwrap();
+        assert_eq!(ttl.scores, chrono::Duration::seconds(60));
+        assert_eq!(ttl.user_data, CacheTtl::default().user_data);
+
+        for invalid in [
+            &[("CACHE_USER_TTL_SECS", "-1")][..],
+            &[("CACHE_SCORE_TTL_SECS", "1h")],
+            &[("CACHE_STALE_WHILE_REVALIDATE_SECS", "")],
+        ] {
+            let error = cache_ttl_from_env(vars(invalid)).err();
+            assert_eq!(error.map(|e| e.name), Some(invalid[0].0.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_database_options_are_validated() {
+        let options = db_options_from_env(vars(&[
+            ("DATABASE_MAX_CONNECTIONS", "10"),
+            ("DATABASE_BUSY_TIMEOUT_MS", "250"),
+            ("DATABASE_WAL", "FALSE"),
+        ]))
+        .unwrap();
+        assert_eq!(options.max_connections, 10);
+        assert_eq!(options.busy_timeout, std::time::Duration::from_millis(250));
+        assert!(!options.wal);
+
+        for invalid in [
+            &[("DATABASE_MAX_CONNECTIONS", "lots")][..],
+            &[("DATABASE_MAX_CONNECTIONS", "0")],
+            &[("DATABASE_BUSY_TIMEOUT_MS", "5s")],
+            &[("DATABASE_WAL", "yes")],
+        ] {
+            let error = db_options_from_env(vars(invalid)).err();
+            assert_eq!(error.map(|e| e.name), Some(invalid[0].0.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_github_settings_are_validated() {
+        let db = Arc::new(Database::in_memory().await.unwrap());
+        let client = build_github_client(
+            "token",
+            db.clone(),
+            vars(&[("GITHUB_CONCURRENCY", "4"), ("GITHUB_MAX_PULL_REQUESTS", "0"), ("GITHUB_DATA_SOURCE", "GraphQL")]),
+        )
+        .unwrap();
+        assert_eq!(client.max_pull_requests(), 0);
+
+        for invalid in [
+            &[("GITHUB_CONCURRENCY", "many")][..],
+            &[("GITHUB_CONCURRENCY", "0")],
+            &[("GITHUB_MAX_PULL_REQUESTS", "-1")],
+            &[("GITHUB_DATA_SOURCE", "soap")],
+        ] {
+            let error = build_github_client("token", db.clone(), vars(invalid)).err();
+            assert_eq!(error.map(|e| e.name), Some(invalid[0].0.to_string()));
+        }
+    }
+}
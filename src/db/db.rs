@@ -1,183 +1,268 @@
-use sqlx::{sqlite::SqlitePool, Row};
 use chrono::{DateTime, Utc, Duration};
-use crate::db::models::{CachedUser, CachedScore};
+use crate::db::models::{BatchItem, BatchJob, CachedUser, CachedScore, CachedResponse, ScoreHistoryEntry};
+use crate::db::sqlite::SqliteStore;
+use crate::db::store::CacheStore;
 use std::path::Path;
-use std::fs;
 use std::env;
+use std::sync::Arc;
+use thiserror::Error;
 
+#[derive(Error, Debug)]
+pub enum DbError {
+    #[error("Database error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Failed to prepare database file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to serialize cached data: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Database schema version {found} is newer than the {supported} this build supports")]
+    SchemaTooNew { found: i64, supported: i64 },
+    #[error("Unsupported database URL '{0}'; expected sqlite: or, with the postgres feature, postgres://")]
+    UnsupportedUrl(String),
+}
+
+/// How long cached entries are served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTtl {
+    pub user_data: Duration,
+    pub scores: Duration,
+    /// How long past its TTL an entry may still be served while it is
+    /// refreshed in the background; zero disables stale-while-revalidate.
+    pub stale_while_revalidate: Duration,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        Self {
+            user_data: Duration::hours(24),
+            scores: Duration::hours(24),
+            stale_while_revalidate: Duration::zero(),
+        }
+    }
+}
+
+impl CacheTtl {
+    /// Whether an entry written at `last_updated` is past `ttl`, and so only
+    /// usable while it is being revalidated.
+    pub fn is_stale(ttl: Duration, last_updated: DateTime<Utc>) -> bool {
+        Utc::now() - last_updated > ttl
+    }
+}
+
+/// Where the cache lives unless `DATABASE_URL` says otherwise.
+pub const DEFAULT_DATABASE_URL: &str = "sqlite:data/github_cache.db";
+
+/// Connection pool settings for [`Database::connect`]. Postgres only uses
+/// `max_connections`; the other settings are SQLite's and are ignored there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbOptions {
+    pub max_connections: u32,
+    /// How long a SQLite statement waits on a lock held by another
+    /// connection before failing with `SQLITE_BUSY`.
+    pub busy_timeout: std::time::Duration,
+    /// SQLite write-ahead logging, so reads are not blocked by a write in
+    /// progress. Ignored for in-memory databases.
+    pub wal: bool,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            busy_timeout: std::time::Duration::from_secs(5),
+            wal: true,
+        }
+    }
+}
+
+/// The cache used by the API: a [`CacheStore`] plus the TTLs entries are
+/// served for.
 pub struct Database {
-    pool: SqlitePool,
+    store: Arc<dyn CacheStore>,
+    ttl: CacheTtl,
 }
 
 impl Database {
-    pub async fn new() -> Result<Self, sqlx::Error> {
-        // Try to use temp directory first
-        let db_path = if let Ok(temp_dir) = env::var("TMPDIR") {
-            Path::new(&temp_dir).join("github_cache.db")
-        } else {
-            // Fallback to current directory
-            env::current_dir()
-                .map_err(|e| sqlx::Error::Configuration(Box::new(e)))?
-                .join("data")
-                .join("github_cache.db")
-        };
+    /// Connects to [`Database::url_from_env`] with default pool options.
+    pub async fn new() -> Result<Self, DbError> {
+        Self::connect(&Self::url_from_env(), DbOptions::default()).await
+    }
 
-        // Create parent directory if it doesn't exist
-        if let Some(parent) = db_path.parent() {
-            if !parent.exists() {
-                println!("Creating directory at: {}", parent.display());
-                fs::create_dir_all(parent)
-                    .map_err(|e| sqlx::Error::Configuration(Box::new(e)))?;
+    /// `DATABASE_URL`, or [`DEFAULT_DATABASE_URL`] when it is not set.
+    pub fn url_from_env() -> String {
+        env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string())
+    }
+
+    /// Connects to a `sqlite:` URL such as `sqlite:data/github_cache.db` or
+    /// `sqlite::memory:`, or with the `postgres` feature a `postgres://` URL,
+    /// and brings the schema up to date.
+    pub async fn connect(url: &str, opts: DbOptions) -> Result<Self, DbError> {
+        if url.starts_with("sqlite:") {
+            return Ok(Self::with_store(SqliteStore::connect(url, opts).await?));
+        }
+        #[cfg(feature = "postgres")]
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let defaults = DbOptions::default();
+            if opts.wal != defaults.wal || opts.busy_timeout != defaults.busy_timeout {
+                println!("WARNING: wal and busy_timeout only apply to SQLite and are ignored for Postgres");
             }
+            return Ok(Self::with_store(crate::db::postgres::PgStore::connect(url, opts).await?));
         }
+        Err(DbError::UnsupportedUrl(url.to_string()))
+    }
 
-        let db_url = format!("sqlite:{}", db_path.display());
-        println!("Connecting to database at: {}", db_url);
+    /// Opens the SQLite file at `db_path` with default pool options.
+    pub async fn open(db_path: &Path) -> Result<Self, DbError> {
+        Ok(Self::with_store(SqliteStore::open(db_path).await?))
+    }
 
-        // Try to create the database file if it doesn't exist
-        if !db_path.exists() {
-            println!("Creating new database file");
-            fs::File::create(&db_path)
-                .map_err(|e| sqlx::Error::Configuration(Box::new(e)))?;
-        }
+    /// Opens a private in-memory database.
+    pub async fn in_memory() -> Result<Self, DbError> {
+        Self::connect("sqlite::memory:", DbOptions::default()).await
+    }
 
-        // Set permissions to read/write
-        let mut perms = fs::metadata(&db_path)
-            .map_err(|e| sqlx::Error::Configuration(Box::new(e)))?
-            .permissions();
-        perms.set_readonly(false);
-        fs::set_permissions(&db_path, perms)
-            .map_err(|e| sqlx::Error::Configuration(Box::new(e)))?;
-
-        // Create a new database connection
-        let pool = SqlitePool::connect(&db_url).await?;
-        
-        // Create tables if they don't exist
-        println!("Creating database tables...");
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS cached_users (
-                username TEXT PRIMARY KEY,
-                user_data TEXT NOT NULL,
-                repositories TEXT NOT NULL,
-                events TEXT NOT NULL,
-                pull_requests TEXT NOT NULL,
-                last_updated TEXT NOT NULL
-            );
-            
-            CREATE TABLE IF NOT EXISTS cached_scores (
-                username TEXT PRIMARY KEY,
-                score TEXT NOT NULL,
-                rating TEXT NOT NULL,
-                stats TEXT NOT NULL,
-                activity TEXT NOT NULL,
-                languages TEXT NOT NULL,
-                last_updated TEXT NOT NULL
-            );
-            "#,
-        )
-        .execute(&pool)
-        .await?;
-
-        println!("Database initialized successfully");
-        Ok(Self { pool })
-    }
-
-    pub async fn get_cached_user(&self, username: &str) -> Result<Option<CachedUser>, sqlx::Error> {
-        let row = sqlx::query(
-            r#"
-            SELECT * FROM cached_users 
-            WHERE username = ? AND last_updated > ?
-            "#,
-        )
-        .bind(username)
-        .bind((Utc::now() - Duration::hours(24)).to_rfc3339())
-        .fetch_optional(&self.pool)
-        .await?;
-
-        if let Some(row) = row {
-            Ok(Some(CachedUser {
-                username: row.get("username"),
-                user_data: serde_json::from_str(row.get("user_data")).unwrap(),
-                repositories: serde_json::from_str(row.get("repositories")).unwrap(),
-                events: serde_json::from_str(row.get("events")).unwrap(),
-                pull_requests: serde_json::from_str(row.get("pull_requests")).unwrap(),
-                last_updated: DateTime::parse_from_rfc3339(row.get("last_updated"))
-                    .unwrap()
-                    .with_timezone(&Utc),
-            }))
-        } else {
-            Ok(None)
+    pub fn with_store(store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ttl: CacheTtl::default(),
         }
     }
 
-    pub async fn cache_user(&self, user: &CachedUser) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO cached_users 
-            (username, user_data, repositories, events, pull_requests, last_updated)
-            VALUES (?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&user.username)
-        .bind(serde_json::to_string(&user.user_data).unwrap())
-        .bind(serde_json::to_string(&user.repositories).unwrap())
-        .bind(serde_json::to_string(&user.events).unwrap())
-        .bind(serde_json::to_string(&user.pull_requests).unwrap())
-        .bind(user.last_updated.to_rfc3339())
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn get_cached_score(&self, username: &str) -> Result<Option<CachedScore>, sqlx::Error> {
-        let row = sqlx::query(
-            r#"
-            SELECT * FROM cached_scores 
-            WHERE username = ? AND last_updated > ?
-            "#,
-        )
-        .bind(username)
-        .bind((Utc::now() - Duration::hours(24)).to_rfc3339())
-        .fetch_optional(&self.pool)
-        .await?;
-
-        if let Some(row) = row {
-            Ok(Some(CachedScore {
-                username: row.get("username"),
-                score: serde_json::from_str(row.get("score")).unwrap(),
-                rating: row.get("rating"),
-                stats: serde_json::from_str(row.get("stats")).unwrap(),
-                activity: serde_json::from_str(row.get("activity")).unwrap(),
-                languages: serde_json::from_str(row.get("languages")).unwrap(),
-                last_updated: DateTime::parse_from_rfc3339(row.get("last_updated"))
-                    .unwrap()
-                    .with_timezone(&Utc),
-            }))
-        } else {
-            Ok(None)
-        }
+    pub fn with_ttl(mut self, ttl: CacheTtl) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn ttl(&self) -> CacheTtl {
+        self.ttl
+    }
+
+    pub fn store(&self) -> &dyn CacheStore {
+        self.store.as_ref()
+    }
+
+    /// Number of unreadable rows found and deleted since startup.
+    pub fn corrupt_rows(&self) -> u64 {
+        self.store.corrupt_rows()
+    }
+
+    /// Returns cached user data that is fresh or still usable while stale;
+    /// check it with [`CacheTtl::is_stale`].
+    pub async fn get_cached_user(&self, username: &str) -> Result<Option<CachedUser>, DbError> {
+        let max_age = self.ttl.user_data + self.ttl.stale_while_revalidate;
+        self.store.get_user(username, max_age).await
     }
 
-    pub async fn cache_score(&self, score: &CachedScore) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO cached_scores 
-            (username, score, rating, stats, activity, languages, last_updated)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&score.username)
-        .bind(serde_json::to_string(&score.score).unwrap())
-        .bind(&score.rating)
-        .bind(serde_json::to_string(&score.stats).unwrap())
-        .bind(serde_json::to_string(&score.activity).unwrap())
-        .bind(serde_json::to_string(&score.languages).unwrap())
-        .bind(score.last_updated.to_rfc3339())
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-} 
\ No newline at end of file
+    pub async fn cache_user(&self, user: &CachedUser) -> Result<(), DbError> {
+        self.store.put_user(user).await
+    }
+
+    /// Returns a cached score that is fresh or still usable while stale;
+    /// check it with [`CacheTtl::is_stale`].
+    pub async fn get_cached_score(
+        &self,
+        username: &str,
+        profile: &str,
+        window_days: u32,
+    ) -> Result<Option<CachedScore>, DbError> {
+        let max_age = self.ttl.scores + self.ttl.stale_while_revalidate;
+        self.store.get_score(username, profile, window_days, max_age).await
+    }
+
+    pub async fn cache_score(&self, score: &CachedScore) -> Result<(), DbError> {
+        self.store.put_score(score).await
+    }
+
+    /// Returns the last response stored for `url`, regardless of age; the
+    /// validators decide whether it is still current.
+    pub async fn get_cached_response(&self, url: &str) -> Result<Option<CachedResponse>, DbError> {
+        self.store.get_response(url).await
+    }
+
+    pub async fn cache_response(&self, response: &CachedResponse) -> Result<(), DbError> {
+        self.store.put_response(response).await
+    }
+
+    pub async fn record_score(&self, entry: &ScoreHistoryEntry) -> Result<(), DbError> {
+        self.store.append_history(entry).await
+    }
+
+    /// Scores computed for a user between `from` and `to`, oldest first.
+    pub async fn score_history(
+        &self,
+        username: &str,
+        profile: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ScoreHistoryEntry>, DbError> {
+        self.store.get_history(username, profile, from, to).await
+    }
+
+    pub async fn create_batch(&self, job: &BatchJob, usernames: &[String]) -> Result<(), DbError> {
+        self.store.create_batch(job, usernames).await
+    }
+
+    pub async fn get_batch(&self, id: &str) -> Result<Option<BatchJob>, DbError> {
+        self.store.get_batch(id).await
+    }
+
+    /// A job's items in the order they were submitted.
+    pub async fn batch_items(&self, job_id: &str) -> Result<Vec<BatchItem>, DbError> {
+        self.store.get_batch_items(job_id).await
+    }
+
+    /// Claims the next item to score, oldest job first, for `lease`. Items a
+    /// crashed or restarted worker was holding come back once their lease
+    /// runs out.
+    pub async fn claim_batch_item(&self, lease: Duration) -> Result<Option<BatchItem>, DbError> {
+        self.store.claim_batch_item(lease).await
+    }
+
+    /// Stores an item's outcome if its worker's claim from `claimed_at` still
+    /// holds; false when another worker has taken the item over.
+    pub async fn update_batch_item(&self, item: &BatchItem, claimed_at: DateTime<Utc>) -> Result<bool, DbError> {
+        self.store.update_batch_item(item, claimed_at).await
+    }
+
+    /// Forgets everything cached for a user, so the next request fetches and
+    /// scores them again. Their score history is kept.
+    pub async fn invalidate_user(&self, username: &str) -> Result<(), DbError> {
+        self.store.invalidate(username).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ttl_limits_reads() {
+        let ttl = CacheTtl {
+            user_data: Duration::hours(1),
+            scores: Duration::hours(1),
+            stale_while_revalidate: Duration::hours(1),
+        };
+        let db = Database::in_memory().await.unwrap().with_ttl(ttl);
+        let mut user = CachedUser {
+            username: "octocat".to_string(),
+            user_data: serde_json::json!({ "login": "octocat" }),
+            repositories: Vec::new(),
+            events: Vec::new(),
+            pull_requests: Vec::new(),
+            last_updated: Utc::now() - Duration::minutes(90),
+        };
+        db.cache_user(&user).await.unwrap();
+        let cached = db.get_cached_user("octocat").await.unwrap().unwrap();
+        assert!(CacheTtl::is_stale(ttl.user_data, cached.last_updated));
+
+        user.last_updated = Utc::now() - Duration::hours(3);
+        db.cache_user(&user).await.unwrap();
+        assert!(db.get_cached_user("octocat").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_url_is_rejected() {
+        let err = Database::connect("mysql://localhost/cache", DbOptions::default()).await.err().unwrap();
+        assert!(matches!(err, DbError::UnsupportedUrl(_)));
+    }
+}
@@ -0,0 +1,279 @@
+//! Versioned schema migrations for the cache database.
+//!
+//! Each migration runs once, in its own transaction, and is recorded in the
+//! `schema_version` table. Databases created before versioning have no such
+//! table and are treated as version 0; the first migration only creates
+//! tables that do not exist yet, so it adopts them as they are.
+//!
+//! SQLite and Postgres have separate migration histories, since Postgres
+//! support was added later.
+
+use super::DbError;
+use chrono::Utc;
+use sqlx::sqlite::SqlitePool;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every SQLite migration, in the order they are applied. Append new ones;
+/// never edit one that has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create cached users and scores",
+        sql: include_str!("../../migrations/0001_create_cache_tables.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "create cached GitHub responses",
+        sql: include_str!("../../migrations/0002_create_cached_responses.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "key cached scores by profile and model version",
+        sql: include_str!("../../migrations/0003_key_scores_by_profile.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "create score history",
+        sql: include_str!("../../migrations/0004_create_score_history.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "create batch jobs",
+        sql: include_str!("../../migrations/0005_create_batch_jobs.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "keep Link headers of cached GitHub responses",
+        sql: include_str!("../../migrations/0006_add_link_to_cached_responses.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "key cached scores by activity window",
+        sql: include_str!("../../migrations/0007_key_scores_by_window.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "claim batch items in the database",
+        sql: include_str!("../../migrations/0008_add_batch_item_claims.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "count batch item attempts",
+        sql: include_str!("../../migrations/0009_add_batch_item_attempts.sql"),
+    },
+];
+
+/// Every Postgres migration, in the order they are applied.
+#[cfg(feature = "postgres")]
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create cache tables",
+        sql: include_str!("../../migrations/postgres/0001_create_cache_tables.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "create score history",
+        sql: include_str!("../../migrations/postgres/0002_create_score_history.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "create batch jobs",
+        sql: include_str!("../../migrations/postgres/0003_create_batch_jobs.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "keep Link headers of cached GitHub responses",
+        sql: include_str!("../../migrations/postgres/0004_add_link_to_cached_responses.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "key cached scores by activity window",
+        sql: include_str!("../../migrations/postgres/0005_key_scores_by_window.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "claim batch items in the database",
+        sql: include_str!("../../migrations/postgres/0006_add_batch_item_claims.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "count batch item attempts",
+        sql: include_str!("../../migrations/postgres/0007_add_batch_item_attempts.sql"),
+    },
+];
+
+const CREATE_SCHEMA_VERSION: &str = r#"
+    CREATE TABLE IF NOT EXISTS schema_version (
+        version INTEGER PRIMARY KEY,
+        description TEXT NOT NULL,
+        applied_at TEXT NOT NULL
+    )
+"#;
+
+/// Schema version this build expects.
+pub fn latest_version(migrations: &[Migration]) -> i64 {
+    migrations.last().map_or(0, |m| m.version)
+}
+
+/// Migrations newer than `current`; fails if `current` is newer than any
+/// this build knows about.
+fn pending(migrations: &[Migration], current: i64) -> Result<&[Migration], DbError> {
+    let supported = latest_version(migrations);
+    if current > supported {
+        return Err(DbError::SchemaTooNew {
+            found: current,
+            supported,
+        });
+    }
+    Ok(&migrations[migrations.partition_point(|m| m.version <= current)..])
+}
+
+/// Version of the SQLite schema `pool` is at; 0 for an unversioned database.
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query(CREATE_SCHEMA_VERSION).execute(pool).await?;
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Applies every SQLite migration newer than the database and returns the
+/// resulting version. A database from a newer build is left untouched and
+/// rejected.
+pub async fn run(pool: &SqlitePool) -> Result<i64, DbError> {
+    let mut version = current_version(pool).await?;
+    for migration in pending(MIGRATIONS, version)? {
+        println!("Applying migration {}: {}", migration.version, migration.description);
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Applies every Postgres migration newer than the database. Replicas
+/// starting together take turns through an advisory lock.
+#[cfg(feature = "postgres")]
+pub async fn run_postgres(pool: &sqlx::PgPool) -> Result<i64, DbError> {
+    use sqlx::{Acquire, Executor};
+
+    /// Advisory lock key held while migrating ("goring" in ASCII).
+    const MIGRATION_LOCK: i64 = 0x676f_7269_6e67;
+
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = async {
+        conn.execute(CREATE_SCHEMA_VERSION).await?;
+        let current: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+            .fetch_one(&mut *conn)
+            .await?;
+        let mut version = current.map_or(0, i64::from);
+        for migration in pending(POSTGRES_MIGRATIONS, version)? {
+            println!("Applying migration {}: {}", migration.version, migration.description);
+            let mut tx = conn.begin().await?;
+            tx.execute(migration.sql).await?;
+            sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES ($1, $2, $3)")
+                .bind(migration.version as i32)
+                .bind(migration.description)
+                .bind(Utc::now().to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            version = migration.version;
+        }
+        Ok(version)
+    }
+    .await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK)
+        .execute(&mut *conn)
+        .await?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CacheTtl, Database, SqliteStore};
+    use chrono::Duration;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 1);
+        }
+        #[cfg(feature = "postgres")]
+        for (i, migration) in POSTGRES_MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 1);
+        }
+        assert_eq!(pending(MIGRATIONS, 1).unwrap()[0].version, 2);
+        assert!(pending(MIGRATIONS, latest_version(MIGRATIONS)).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_from_v0_database() {
+        let path = std::env::temp_dir().join(format!("github_cache-v0-{}.db", std::process::id()));
+        std::fs::copy("tests/fixtures/db/v0.db", &path).unwrap();
+
+        let ttl = CacheTtl {
+            user_data: Duration::days(365 * 100),
+            ..CacheTtl::default()
+        };
+        let db = Database::open(&path).await.unwrap().with_ttl(ttl);
+        let store = SqliteStore::open(&path).await.unwrap();
+        assert_eq!(current_version(store.pool()).await.unwrap(), latest_version(MIGRATIONS));
+
+        // Fetched GitHub data survives the upgrade
+        let user = db.get_cached_user("fresh-start").await.unwrap().unwrap();
+        assert_eq!(user.user_data["login"], "fresh-start");
+        assert!(!user.repositories.is_empty());
+
+        // Scores from before profiles are dropped
+        let scores: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cached_scores")
+            .fetch_one(store.pool())
+            .await
+            .unwrap();
+        assert_eq!(scores, 0);
+        assert!(db.get_cached_score("fresh-start", "default", 30).await.unwrap().is_none());
+        drop(db);
+
+        // Reopening applies nothing further
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version")
+            .fetch_one(store.pool())
+            .await
+            .unwrap();
+        assert_eq!(applied, latest_version(MIGRATIONS));
+        store.pool().close().await;
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_newer_schema_is_rejected() {
+        let store = SqliteStore::connect("sqlite::memory:", Default::default()).await.unwrap();
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, 'future', '')")
+            .bind(latest_version(MIGRATIONS) + 1)
+            .execute(store.pool())
+            .await
+            .unwrap();
+        assert!(matches!(run(store.pool()).await, Err(DbError::SchemaTooNew { .. })));
+    }
+}
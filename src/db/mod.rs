@@ -1,4 +1,14 @@
 pub mod models;
+pub mod migrations;
+pub mod store;
+pub mod sqlite;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[allow(clippy::module_inception)]
 pub mod db;
  
-pub use db::Database; 
\ No newline at end of file
+pub use db::{CacheTtl, Database, DbError, DbOptions, DEFAULT_DATABASE_URL};
+pub use sqlite::SqliteStore;
+pub use store::CacheStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PgStore;
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CachedUser {
@@ -14,10 +15,110 @@ pub struct CachedUser {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CachedScore {
     pub username: String,
+    /// Scoring profile the score was computed with, and its version.
+    pub profile: String,
+    /// Activity window the score's stats cover; part of the cache key.
+    pub window_days: u32,
+    pub profile_version: String,
+    /// Algorithm and config the score was computed with; see
+    /// `GitHubScorer::model_version`.
+    pub model_version: String,
     pub score: serde_json::Value,
     pub rating: String,
     pub stats: serde_json::Value,
     pub activity: serde_json::Value,
     pub languages: serde_json::Value,
     pub last_updated: DateTime<Utc>,
-} 
\ No newline at end of file
+} 
+
+/// A GitHub API response body kept for conditional requests.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// The `Link` header, which GitHub does not always repeat on a 304.
+    #[serde(default)]
+    pub link: Option<String>,
+    pub body: String,
+    pub last_updated: DateTime<Utc>,
+}
+
+/// A score as it was computed at one point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreHistoryEntry {
+    pub username: String,
+    pub profile: String,
+    pub model_version: String,
+    pub final_score: f64,
+    /// Score of each component, by name.
+    pub component_scores: serde_json::Value,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// A list of users submitted to be scored in the background.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub id: String,
+    pub profile: String,
+    pub window_days: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchItemStatus {
+    Pending,
+    /// Claimed by a worker that is scoring it.
+    Running,
+    Done,
+    Failed,
+}
+
+impl BatchItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for BatchItemStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "running" => Ok(Self::Running),
+            "done" => Ok(Self::Done),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("unknown batch item status '{}'", other)),
+        }
+    }
+}
+
+/// One user in a batch job, with their score once it has been computed or
+/// the reason it could not be.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub job_id: String,
+    /// Place of the user in the submitted list, from 0.
+    pub position: u32,
+    pub username: String,
+    pub status: BatchItemStatus,
+    pub final_score: Option<f64>,
+    pub rating: Option<String>,
+    /// Score of each component, by name.
+    pub component_scores: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    /// When a worker last claimed the item.
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// A pending item is not claimed again before this time.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Failed tries that were put back to be tried again.
+    pub attempts: u32,
+}
@@ -0,0 +1,503 @@
+use crate::db::migrations;
+use crate::db::models::{
+    BatchItem, BatchItemStatus, BatchJob, CachedResponse, CachedScore, CachedUser, ScoreHistoryEntry,
+};
+use crate::db::store::{
+    decode_batch_item, decode_batch_job, decode_history, decode_response, decode_score, decode_user, CacheStore,
+};
+use crate::db::{DbError, DbOptions};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A cache store in Postgres, which several API replicas can share.
+pub struct PgStore {
+    pool: PgPool,
+    corrupt_rows: AtomicU64,
+}
+
+impl PgStore {
+    /// Connects to a `postgres://` URL and brings the schema up to date.
+    pub async fn connect(url: &str, opts: DbOptions) -> Result<Self, DbError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(opts.max_connections.max(1))
+            .connect(url)
+            .await?;
+        let version = migrations::run_postgres(&pool).await?;
+        println!("Database initialized successfully (postgres schema version {})", version);
+        Ok(Self {
+            pool,
+            corrupt_rows: AtomicU64::new(0),
+        })
+    }
+
+    /// Deletes a row that could not be decoded, so the entry is fetched again
+    /// rather than failing every time it is read.
+    async fn discard_corrupt_row(
+        &self,
+        table: &str,
+        key_columns: &[&str],
+        key: &[&str],
+        message: &str,
+    ) -> Result<(), DbError> {
+        self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+        println!("Discarding corrupt row in {} for {}: {}", table, key.join("/"), message);
+
+        let condition = key_columns
+            .iter()
+            .enumerate()
+            // Keys are bound as text, so non-text key columns are compared as text
+            .map(|(i, column)| format!("{}::text = ${}", column, i + 1))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let sql = format!("DELETE FROM {} WHERE {}", table, condition);
+        let mut query = sqlx::query(&sql);
+        for value in key {
+            query = query.bind(*value);
+        }
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Decodes batch item rows, skipping and counting unreadable ones.
+    fn decode_batch_items(&self, rows: &[PgRow]) -> Vec<BatchItem> {
+        let mut items = Vec::with_capacity(rows.len());
+        for row in rows {
+            match decode_batch_item(row) {
+                Ok(item) => items.push(item),
+                Err(message) => {
+                    self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                    println!("Skipping corrupt batch item row: {}", message);
+                }
+            }
+        }
+        items
+    }
+}
+
+#[async_trait]
+impl CacheStore for PgStore {
+    async fn get_user(&self, username: &str, max_age: Duration) -> Result<Option<CachedUser>, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM cached_users
+            WHERE username = $1 AND last_updated > $2
+            "#,
+        )
+        .bind(username)
+        .bind((Utc::now() - max_age).to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_user(&row) {
+            Ok(user) => Ok(Some(user)),
+            Err(message) => {
+                self.discard_corrupt_row("cached_users", &["username"], &[username], &message).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_user(&self, user: &CachedUser) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO cached_users
+            (username, user_data, repositories, events, pull_requests, last_updated)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (username) DO UPDATE SET
+                user_data = EXCLUDED.user_data,
+                repositories = EXCLUDED.repositories,
+                events = EXCLUDED.events,
+                pull_requests = EXCLUDED.pull_requests,
+                last_updated = EXCLUDED.last_updated
+            "#,
+        )
+        .bind(&user.username)
+        .bind(serde_json::to_string(&user.user_data)?)
+        .bind(serde_json::to_string(&user.repositories)?)
+        .bind(serde_json::to_string(&user.events)?)
+        .bind(serde_json::to_string(&user.pull_requests)?)
+        .bind(user.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_score(
+        &self,
+        username: &str,
+        profile: &str,
+        window_days: u32,
+        max_age: Duration,
+    ) -> Result<Option<CachedScore>, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM cached_scores
+            WHERE username = $1 AND profile = $2 AND window_days = $3 AND last_updated > $4
+            "#,
+        )
+        .bind(username)
+        .bind(profile)
+        .bind(window_days as i64)
+        .bind((Utc::now() - max_age).to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_score(&row) {
+            Ok(score) => Ok(Some(score)),
+            Err(message) => {
+                let window = window_days.to_string();
+                self.discard_corrupt_row(
+                    "cached_scores",
+                    &["username", "profile", "window_days"],
+                    &[username, profile, &window],
+                    &message,
+                )
+                .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_score(&self, score: &CachedScore) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO cached_scores
+            (username, profile, window_days, profile_version, model_version, score, rating, stats, activity, languages, last_updated)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (username, profile, window_days) DO UPDATE SET
+                profile_version = EXCLUDED.profile_version,
+                model_version = EXCLUDED.model_version,
+                score = EXCLUDED.score,
+                rating = EXCLUDED.rating,
+                stats = EXCLUDED.stats,
+                activity = EXCLUDED.activity,
+                languages = EXCLUDED.languages,
+                last_updated = EXCLUDED.last_updated
+            "#,
+        )
+        .bind(&score.username)
+        .bind(&score.profile)
+        .bind(score.window_days as i64)
+        .bind(&score.profile_version)
+        .bind(&score.model_version)
+        .bind(serde_json::to_string(&score.score)?)
+        .bind(&score.rating)
+        .bind(serde_json::to_string(&score.stats)?)
+        .bind(serde_json::to_string(&score.activity)?)
+        .bind(serde_json::to_string(&score.languages)?)
+        .bind(score.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_response(&self, url: &str) -> Result<Option<CachedResponse>, DbError> {
+        let row = sqlx::query("SELECT * FROM cached_responses WHERE url = $1")
+            .bind(url)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_response(&row) {
+            Ok(response) => Ok(Some(response)),
+            Err(message) => {
+                self.discard_corrupt_row("cached_responses", &["url"], &[url], &message).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_response(&self, response: &CachedResponse) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO cached_responses
+            (url, etag, last_modified, link, body, last_updated)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (url) DO UPDATE SET
+                etag = EXCLUDED.etag,
+                last_modified = EXCLUDED.last_modified,
+                link = EXCLUDED.link,
+                body = EXCLUDED.body,
+                last_updated = EXCLUDED.last_updated
+            "#,
+        )
+        .bind(&response.url)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(&response.link)
+        .bind(&response.body)
+        .bind(response.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn invalidate(&self, username: &str) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM cached_users WHERE username = $1")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM cached_scores WHERE username = $1")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn append_history(&self, entry: &ScoreHistoryEntry) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO score_history
+            (username, profile, model_version, final_score, component_scores, computed_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&entry.username)
+        .bind(&entry.profile)
+        .bind(&entry.model_version)
+        .bind(entry.final_score)
+        .bind(serde_json::to_string(&entry.component_scores)?)
+        .bind(entry.computed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_history(
+        &self,
+        username: &str,
+        profile: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ScoreHistoryEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM score_history
+            WHERE username = $1 AND profile = $2 AND computed_at >= $3 AND computed_at <= $4
+            ORDER BY computed_at, id
+            "#,
+        )
+        .bind(username)
+        .bind(profile)
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in &rows {
+            match decode_history(row) {
+                Ok(entry) => entries.push(entry),
+                Err(message) => {
+                    self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                    println!("Skipping corrupt score history row for {}: {}", username, message);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn create_batch(&self, job: &BatchJob, usernames: &[String]) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO batch_jobs (id, profile, window_days, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(&job.id)
+            .bind(&job.profile)
+            .bind(job.window_days as i64)
+            .bind(job.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        for (position, username) in usernames.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO batch_items (job_id, position, username, status, updated_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(&job.id)
+            .bind(position as i64)
+            .bind(username)
+            .bind(BatchItemStatus::Pending.as_str())
+            .bind(job.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_batch(&self, id: &str) -> Result<Option<BatchJob>, DbError> {
+        let row = sqlx::query("SELECT * FROM batch_jobs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_batch_job(&row) {
+            Ok(job) => Ok(Some(job)),
+            Err(message) => {
+                self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                println!("Skipping corrupt batch job {}: {}", id, message);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn get_batch_items(&self, job_id: &str) -> Result<Vec<BatchItem>, DbError> {
+        let rows = sqlx::query("SELECT * FROM batch_items WHERE job_id = $1 ORDER BY position")
+            .bind(job_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(self.decode_batch_items(&rows))
+    }
+
+    async fn claim_batch_item(&self, lease: Duration) -> Result<Option<BatchItem>, DbError> {
+        let now = Utc::now();
+        // Rows another replica is claiming right now are locked and skipped
+        let rows = sqlx::query(
+            r#"
+            UPDATE batch_items SET status = $1, claimed_at = $2, updated_at = $2
+            WHERE (job_id, position) = (
+                SELECT batch_items.job_id, batch_items.position FROM batch_items
+                JOIN batch_jobs ON batch_jobs.id = batch_items.job_id
+                WHERE (batch_items.status = $3 AND (batch_items.next_attempt_at IS NULL OR batch_items.next_attempt_at <= $2))
+                    OR (batch_items.status = $1 AND batch_items.claimed_at <= $4)
+                ORDER BY batch_jobs.created_at, batch_items.job_id, batch_items.position
+                LIMIT 1
+                FOR UPDATE OF batch_items SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(BatchItemStatus::Running.as_str())
+        .bind(now.to_rfc3339())
+        .bind(BatchItemStatus::Pending.as_str())
+        .bind((now - lease).to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(self.decode_batch_items(&rows).into_iter().next())
+    }
+
+    async fn update_batch_item(&self, item: &BatchItem, claimed_at: DateTime<Utc>) -> Result<bool, DbError> {
+        let component_scores = item.component_scores.as_ref().map(serde_json::to_string).transpose()?;
+        let result = sqlx::query(
+            r#"
+            UPDATE batch_items
+            SET status = $1, final_score = $2, rating = $3, component_scores = $4, error = $5, updated_at = $6,
+                claimed_at = $7, next_attempt_at = $8, attempts = $9
+            WHERE job_id = $10 AND position = $11 AND claimed_at = $12
+            "#,
+        )
+        .bind(item.status.as_str())
+        .bind(item.final_score)
+        .bind(&item.rating)
+        .bind(component_scores)
+        .bind(&item.error)
+        .bind(item.updated_at.to_rfc3339())
+        .bind(item.claimed_at.map(|t| t.to_rfc3339()))
+        .bind(item.next_attempt_at.map(|t| t.to_rfc3339()))
+        .bind(item.attempts as i64)
+        .bind(&item.job_id)
+        .bind(item.position as i64)
+        .bind(claimed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn corrupt_rows(&self) -> u64 {
+        self.corrupt_rows.load(Ordering::Relaxed)
+    }
+}
+
+/// These run against the server in `TEST_POSTGRES_URL`, each in a database of
+/// its own; `scripts/test-postgres.sh` starts a throwaway server, sets it and
+/// runs them, as they are ignored by default.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::store::tests::check_cache_store;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A fresh, empty database on the test server.
+    async fn test_database() -> String {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let admin_url = std::env::var("TEST_POSTGRES_URL")
+            .expect("TEST_POSTGRES_URL must name a Postgres server; see scripts/test-postgres.sh");
+        let name = format!(
+            "goring_test_{}_{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let admin = PgPool::connect(&admin_url).await.unwrap();
+        sqlx::query(&format!("DROP DATABASE IF EXISTS {}", name))
+            .execute(&admin)
+            .await
+            .unwrap();
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&admin)
+            .await
+            .unwrap();
+        admin.close().await;
+
+        let (base, _) = admin_url.rsplit_once('/').unwrap();
+        format!("{}/{}", base, name)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL; run scripts/test-postgres.sh"]
+    async fn test_postgres_store() {
+        let url = test_database().await;
+        let store = PgStore::connect(&url, DbOptions::default()).await.unwrap();
+        check_cache_store(&store).await;
+
+        // Connecting again finds the schema current
+        let again = PgStore::connect(&url, DbOptions::default()).await.unwrap();
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version")
+            .fetch_one(&again.pool)
+            .await
+            .unwrap();
+        assert_eq!(applied, migrations::latest_version(migrations::POSTGRES_MIGRATIONS));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL; run scripts/test-postgres.sh"]
+    async fn test_postgres_corrupt_rows_are_misses_and_deleted() {
+        let url = test_database().await;
+        let store = PgStore::connect(&url, DbOptions::default()).await.unwrap();
+        sqlx::query(
+            "INSERT INTO cached_scores VALUES ('octocat', 'default', 30, 'v', 'm', '{', 'Novice', '{}', '{}', '{}', $1)",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        assert!(store.get_score("octocat", "default", 30, Duration::hours(1)).await.unwrap().is_none());
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cached_scores")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+        assert_eq!(store.corrupt_rows(), 1);
+    }
+}
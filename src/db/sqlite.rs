@@ -0,0 +1,558 @@
+use crate::db::migrations;
+use crate::db::models::{
+    BatchItem, BatchItemStatus, BatchJob, CachedResponse, CachedScore, CachedUser, ScoreHistoryEntry,
+};
+use crate::db::store::{
+    decode_batch_item, decode_batch_job, decode_history, decode_response, decode_score, decode_user, CacheStore,
+};
+use crate::db::{DbError, DbOptions};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteRow};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The default cache store: a SQLite file, or memory for tests.
+pub struct SqliteStore {
+    pool: SqlitePool,
+    corrupt_rows: AtomicU64,
+}
+
+impl SqliteStore {
+    /// Connects to a SQLite URL such as `sqlite:data/github_cache.db` or
+    /// `sqlite::memory:`, creating the file and its directory if needed, and
+    /// brings the schema up to date.
+    pub async fn connect(url: &str, opts: DbOptions) -> Result<Self, DbError> {
+        let in_memory = url.contains(":memory:") || url.contains("mode=memory");
+        let mut connect_options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .busy_timeout(opts.busy_timeout);
+        if opts.wal && !in_memory {
+            connect_options = connect_options.journal_mode(SqliteJournalMode::Wal);
+        }
+
+        let mut pool_options = SqlitePoolOptions::new().max_connections(opts.max_connections.max(1));
+        if in_memory {
+            // The database only lives as long as its connections, so keep them open
+            pool_options = pool_options.min_connections(1).idle_timeout(None).max_lifetime(None);
+            println!("Using in-memory database");
+        } else {
+            let db_path = connect_options.clone().get_filename();
+            if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                if !parent.exists() {
+                    println!("Creating directory at: {}", parent.display());
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            if db_path.exists() {
+                // Set permissions to read/write
+                let mut perms = fs::metadata(&db_path)?.permissions();
+                #[allow(clippy::permissions_set_readonly_false)]
+                perms.set_readonly(false);
+                fs::set_permissions(&db_path, perms)?;
+            }
+            println!("Connecting to database at: {}", db_path.display());
+        }
+
+        let pool = pool_options.connect_with(connect_options).await?;
+        let version = migrations::run(&pool).await?;
+        println!("Database initialized successfully (schema version {})", version);
+        Ok(Self {
+            pool,
+            corrupt_rows: AtomicU64::new(0),
+        })
+    }
+
+    /// Opens the database file at `db_path` with default pool options.
+    pub async fn open(db_path: &Path) -> Result<Self, DbError> {
+        let url = format!("sqlite:{}", db_path.display());
+        Self::connect(&url, DbOptions::default()).await
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Deletes a row that could not be decoded, so the entry is fetched again
+    /// rather than failing every time it is read.
+    async fn discard_corrupt_row(
+        &self,
+        table: &str,
+        key_columns: &[&str],
+        key: &[&str],
+        message: &str,
+    ) -> Result<(), DbError> {
+        self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+        println!("Discarding corrupt row in {} for {}: {}", table, key.join("/"), message);
+
+        let condition = key_columns
+            .iter()
+            .map(|column| format!("{} = ?", column))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let sql = format!("DELETE FROM {} WHERE {}", table, condition);
+        let mut query = sqlx::query(&sql);
+        for value in key {
+            query = query.bind(*value);
+        }
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Decodes batch item rows, skipping and counting unreadable ones.
+    fn decode_batch_items(&self, rows: &[SqliteRow]) -> Vec<BatchItem> {
+        let mut items = Vec::with_capacity(rows.len());
+        for row in rows {
+            match decode_batch_item(row) {
+                Ok(item) => items.push(item),
+                Err(message) => {
+                    self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                    println!("Skipping corrupt batch item row: {}", message);
+                }
+            }
+        }
+        items
+    }
+}
+
+#[async_trait]
+impl CacheStore for SqliteStore {
+    async fn get_user(&self, username: &str, max_age: Duration) -> Result<Option<CachedUser>, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM cached_users
+            WHERE username = ? AND last_updated > ?
+            "#,
+        )
+        .bind(username)
+        .bind((Utc::now() - max_age).to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_user(&row) {
+            Ok(user) => Ok(Some(user)),
+            Err(message) => {
+                self.discard_corrupt_row("cached_users", &["username"], &[username], &message).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_user(&self, user: &CachedUser) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO cached_users
+            (username, user_data, repositories, events, pull_requests, last_updated)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&user.username)
+        .bind(serde_json::to_string(&user.user_data)?)
+        .bind(serde_json::to_string(&user.repositories)?)
+        .bind(serde_json::to_string(&user.events)?)
+        .bind(serde_json::to_string(&user.pull_requests)?)
+        .bind(user.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_score(
+        &self,
+        username: &str,
+        profile: &str,
+        window_days: u32,
+        max_age: Duration,
+    ) -> Result<Option<CachedScore>, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM cached_scores
+            WHERE username = ? AND profile = ? AND window_days = ? AND last_updated > ?
+            "#,
+        )
+        .bind(username)
+        .bind(profile)
+        .bind(window_days as i64)
+        .bind((Utc::now() - max_age).to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_score(&row) {
+            Ok(score) => Ok(Some(score)),
+            Err(message) => {
+                let window = window_days.to_string();
+                self.discard_corrupt_row(
+                    "cached_scores",
+                    &["username", "profile", "window_days"],
+                    &[username, profile, &window],
+                    &message,
+                )
+                .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_score(&self, score: &CachedScore) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO cached_scores
+            (username, profile, window_days, profile_version, model_version, score, rating, stats, activity, languages, last_updated)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&score.username)
+        .bind(&score.profile)
+        .bind(score.window_days as i64)
+        .bind(&score.profile_version)
+        .bind(&score.model_version)
+        .bind(serde_json::to_string(&score.score)?)
+        .bind(&score.rating)
+        .bind(serde_json::to_string(&score.stats)?)
+        .bind(serde_json::to_string(&score.activity)?)
+        .bind(serde_json::to_string(&score.languages)?)
+        .bind(score.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_response(&self, url: &str) -> Result<Option<CachedResponse>, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM cached_responses
+            WHERE url = ?
+            "#,
+        )
+        .bind(url)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_response(&row) {
+            Ok(response) => Ok(Some(response)),
+            Err(message) => {
+                self.discard_corrupt_row("cached_responses", &["url"], &[url], &message).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn put_response(&self, response: &CachedResponse) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO cached_responses
+            (url, etag, last_modified, link, body, last_updated)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&response.url)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(&response.link)
+        .bind(&response.body)
+        .bind(response.last_updated.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn invalidate(&self, username: &str) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM cached_users WHERE username = ?")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM cached_scores WHERE username = ?")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn append_history(&self, entry: &ScoreHistoryEntry) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO score_history
+            (username, profile, model_version, final_score, component_scores, computed_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.username)
+        .bind(&entry.profile)
+        .bind(&entry.model_version)
+        .bind(entry.final_score)
+        .bind(serde_json::to_string(&entry.component_scores)?)
+        .bind(entry.computed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_history(
+        &self,
+        username: &str,
+        profile: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ScoreHistoryEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM score_history
+            WHERE username = ? AND profile = ? AND computed_at >= ? AND computed_at <= ?
+            ORDER BY computed_at, id
+            "#,
+        )
+        .bind(username)
+        .bind(profile)
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in &rows {
+            match decode_history(row) {
+                Ok(entry) => entries.push(entry),
+                Err(message) => {
+                    self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                    println!("Skipping corrupt score history row for {}: {}", username, message);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn create_batch(&self, job: &BatchJob, usernames: &[String]) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO batch_jobs (id, profile, window_days, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&job.id)
+            .bind(&job.profile)
+            .bind(job.window_days as i64)
+            .bind(job.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        for (position, username) in usernames.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO batch_items (job_id, position, username, status, updated_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&job.id)
+            .bind(position as i64)
+            .bind(username)
+            .bind(BatchItemStatus::Pending.as_str())
+            .bind(job.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_batch(&self, id: &str) -> Result<Option<BatchJob>, DbError> {
+        let row = sqlx::query("SELECT * FROM batch_jobs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        match decode_batch_job(&row) {
+            Ok(job) => Ok(Some(job)),
+            Err(message) => {
+                self.corrupt_rows.fetch_add(1, Ordering::Relaxed);
+                println!("Skipping corrupt batch job {}: {}", id, message);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn get_batch_items(&self, job_id: &str) -> Result<Vec<BatchItem>, DbError> {
+        let rows = sqlx::query("SELECT * FROM batch_items WHERE job_id = ? ORDER BY position")
+            .bind(job_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(self.decode_batch_items(&rows))
+    }
+
+    async fn claim_batch_item(&self, lease: Duration) -> Result<Option<BatchItem>, DbError> {
+        let now = Utc::now();
+        // SQLite runs one write at a time, so selecting and claiming the item
+        // in a single statement is atomic
+        let rows = sqlx::query(
+            r#"
+            UPDATE batch_items SET status = ?, claimed_at = ?, updated_at = ?
+            WHERE (job_id, position) = (
+                SELECT batch_items.job_id, batch_items.position FROM batch_items
+                JOIN batch_jobs ON batch_jobs.id = batch_items.job_id
+                WHERE (batch_items.status = ? AND (batch_items.next_attempt_at IS NULL OR batch_items.next_attempt_at <= ?))
+                    OR (batch_items.status = ? AND batch_items.claimed_at <= ?)
+                ORDER BY batch_jobs.created_at, batch_items.job_id, batch_items.position
+                LIMIT 1
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(BatchItemStatus::Running.as_str())
+        .bind(now.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .bind(BatchItemStatus::Pending.as_str())
+        .bind(now.to_rfc3339())
+        .bind(BatchItemStatus::Running.as_str())
+        .bind((now - lease).to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(self.decode_batch_items(&rows).into_iter().next())
+    }
+
+    async fn update_batch_item(&self, item: &BatchItem, claimed_at: DateTime<Utc>) -> Result<bool, DbError> {
+        let component_scores = item.component_scores.as_ref().map(serde_json::to_string).transpose()?;
+        let result = sqlx::query(
+            r#"
+            UPDATE batch_items
+            SET status = ?, final_score = ?, rating = ?, component_scores = ?, error = ?, updated_at = ?,
+                claimed_at = ?, next_attempt_at = ?, attempts = ?
+            WHERE job_id = ? AND position = ? AND claimed_at = ?
+            "#,
+        )
+        .bind(item.status.as_str())
+        .bind(item.final_score)
+        .bind(&item.rating)
+        .bind(component_scores)
+        .bind(&item.error)
+        .bind(item.updated_at.to_rfc3339())
+        .bind(item.claimed_at.map(|t| t.to_rfc3339()))
+        .bind(item.next_attempt_at.map(|t| t.to_rfc3339()))
+        .bind(item.attempts as i64)
+        .bind(&item.job_id)
+        .bind(item.position as i64)
+        .bind(claimed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn corrupt_rows(&self) -> u64 {
+        self.corrupt_rows.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::store::tests::check_cache_store;
+
+    async fn memory_store() -> SqliteStore {
+        SqliteStore::connect("sqlite::memory:", DbOptions::default()).await.unwrap()
+    }
+
+    async fn count(store: &SqliteStore, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        check_cache_store(&memory_store().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_rows_are_misses_and_deleted() {
+        let store = memory_store().await;
+        let now = Utc::now().to_rfc3339();
+        let max_age = Duration::hours(1);
+
+        sqlx::query("INSERT INTO cached_users VALUES ('octocat', '{\"login\":', '[]', '[]', '[]', ?)")
+            .bind(&now)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        assert!(store.get_user("octocat", max_age).await.unwrap().is_none());
+        assert_eq!(count(&store, "cached_users").await, 0);
+
+        sqlx::query(
+            "INSERT INTO cached_scores VALUES ('octocat', 'default', 30, 'v', 'm', '{}', 'Novice', '{}', '{}', '{}', 'yesterday')",
+        )
+        .execute(&store.pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO cached_scores VALUES ('octocat', 'default', 7, 'v', 'm', '{}', 'Novice', '{}', '{}', '{}', ?)")
+            .bind(&now)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        assert!(store.get_score("octocat", "default", 30, max_age).await.unwrap().is_none());
+        // Only the corrupt window's row is removed
+        assert!(store.get_score("octocat", "default", 7, max_age).await.unwrap().is_some());
+        assert_eq!(count(&store, "cached_scores").await, 1);
+
+        sqlx::query("INSERT INTO cached_responses (url, body, last_updated) VALUES ('https://api.github.com/users/octocat', 42, 'soon')")
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        assert!(store.get_response("https://api.github.com/users/octocat").await.unwrap().is_none());
+        assert_eq!(count(&store, "cached_responses").await, 0);
+
+        assert_eq!(store.corrupt_rows(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_connect_creates_file_with_options() {
+        let dir = std::env::temp_dir().join(format!("github-cache-connect-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let url = format!("sqlite:{}", dir.join("nested").join("cache.db").display());
+
+        let opts = DbOptions {
+            max_connections: 2,
+            ..DbOptions::default()
+        };
+        let store = SqliteStore::connect(&url, opts).await.unwrap();
+        assert!(dir.join("nested").join("cache.db").exists());
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(store.pool.options().get_max_connections(), 2);
+        store.pool.close().await;
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_url_is_shared_across_connections() {
+        let opts = DbOptions {
+            max_connections: 4,
+            ..DbOptions::default()
+        };
+        let store = SqliteStore::connect("sqlite::memory:", opts).await.unwrap();
+
+        // Hold one connection so the next query has to use another
+        let mut held = store.pool.acquire().await.unwrap();
+        sqlx::query("SELECT 1").execute(&mut *held).await.unwrap();
+        assert_eq!(count(&store, "cached_users").await, 0);
+    }
+}
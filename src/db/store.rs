@@ -0,0 +1,410 @@
+use crate::db::models::{BatchItem, BatchJob, CachedResponse, CachedScore, CachedUser, ScoreHistoryEntry};
+use crate::db::DbError;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use sqlx::{ColumnIndex, Decode, Row, Type};
+
+/// Storage behind [`crate::db::Database`].
+///
+/// Reads skip entries older than `max_age`. A row that cannot be decoded is
+/// deleted and reported as a miss, and counted in [`CacheStore::corrupt_rows`].
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get_user(&self, username: &str, max_age: Duration) -> Result<Option<CachedUser>, DbError>;
+
+    async fn put_user(&self, user: &CachedUser) -> Result<(), DbError>;
+
+    async fn get_score(
+        &self,
+        username: &str,
+        profile: &str,
+        window_days: u32,
+        max_age: Duration,
+    ) -> Result<Option<CachedScore>, DbError>;
+
+    async fn put_score(&self, score: &CachedScore) -> Result<(), DbError>;
+
+    /// The last response stored for `url`, regardless of age.
+    async fn get_response(&self, url: &str) -> Result<Option<CachedResponse>, DbError>;
+
+    async fn put_response(&self, response: &CachedResponse) -> Result<(), DbError>;
+
+    /// Drops a user's cached data and scores. Their score history is kept.
+    async fn invalidate(&self, username: &str) -> Result<(), DbError>;
+
+    /// Records a computed score; history is only ever appended to.
+    async fn append_history(&self, entry: &ScoreHistoryEntry) -> Result<(), DbError>;
+
+    /// A user's scores under `profile` computed between `from` and `to`
+    /// inclusive, oldest first. History is a record rather than a cache, so
+    /// unreadable rows are skipped and counted but not deleted.
+    async fn get_history(
+        &self,
+        username: &str,
+        profile: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ScoreHistoryEntry>, DbError>;
+
+    /// Stores a new batch job with a pending item for each username, in order.
+    async fn create_batch(&self, job: &BatchJob, usernames: &[String]) -> Result<(), DbError>;
+
+    async fn get_batch(&self, id: &str) -> Result<Option<BatchJob>, DbError>;
+
+    /// A job's items in the order they were submitted. Like history, batch
+    /// results are a record, so unreadable rows are skipped but not deleted.
+    async fn get_batch_items(&self, job_id: &str) -> Result<Vec<BatchItem>, DbError>;
+
+    /// Atomically claims the next item to score across all jobs, oldest job
+    /// first, and marks it running. Pending items are claimable once their
+    /// `next_attempt_at` has passed, running ones once their claim is older
+    /// than `lease`, so no two workers hold the same item at a time.
+    async fn claim_batch_item(&self, lease: Duration) -> Result<Option<BatchItem>, DbError>;
+
+    /// Stores the outcome of an item, found by its job and position, if it is
+    /// still held under the claim its worker took at `claimed_at`. False when
+    /// the lease ran out and another worker claimed the item since.
+    async fn update_batch_item(&self, item: &BatchItem, claimed_at: DateTime<Utc>) -> Result<bool, DbError>;
+
+    /// Number of unreadable rows found and deleted since startup.
+    fn corrupt_rows(&self) -> u64;
+}
+
+// Rows are decoded the same way for every backend: JSON documents and
+// RFC 3339 timestamps are stored as text. Errors describe the bad column.
+
+pub(crate) fn decode_user<R: Row>(row: &R) -> Result<CachedUser, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Ok(CachedUser {
+        username: column(row, "username")?,
+        user_data: json_column(row, "user_data")?,
+        repositories: json_column(row, "repositories")?,
+        events: json_column(row, "events")?,
+        pull_requests: json_column(row, "pull_requests")?,
+        last_updated: timestamp_column(row, "last_updated")?,
+    })
+}
+
+pub(crate) fn decode_score<R: Row>(row: &R) -> Result<CachedScore, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Ok(CachedScore {
+        username: column(row, "username")?,
+        profile: column(row, "profile")?,
+        window_days: integer_column(row, "window_days")?,
+        profile_version: column(row, "profile_version")?,
+        model_version: column(row, "model_version")?,
+        score: json_column(row, "score")?,
+        rating: column(row, "rating")?,
+        stats: json_column(row, "stats")?,
+        activity: json_column(row, "activity")?,
+        languages: json_column(row, "languages")?,
+        last_updated: timestamp_column(row, "last_updated")?,
+    })
+}
+
+pub(crate) fn decode_response<R: Row>(row: &R) -> Result<CachedResponse, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> Option<String>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Ok(CachedResponse {
+        url: column(row, "url")?,
+        etag: column(row, "etag")?,
+        last_modified: column(row, "last_modified")?,
+        link: column(row, "link")?,
+        body: column(row, "body")?,
+        last_updated: timestamp_column(row, "last_updated")?,
+    })
+}
+
+pub(crate) fn decode_history<R: Row>(row: &R) -> Result<ScoreHistoryEntry, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f64: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Ok(ScoreHistoryEntry {
+        username: column(row, "username")?,
+        profile: column(row, "profile")?,
+        model_version: column(row, "model_version")?,
+        final_score: column(row, "final_score")?,
+        component_scores: json_column(row, "component_scores")?,
+        computed_at: timestamp_column(row, "computed_at")?,
+    })
+}
+
+pub(crate) fn decode_batch_job<R: Row>(row: &R) -> Result<BatchJob, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Ok(BatchJob {
+        id: column(row, "id")?,
+        profile: column(row, "profile")?,
+        window_days: integer_column(row, "window_days")?,
+        created_at: timestamp_column(row, "created_at")?,
+    })
+}
+
+pub(crate) fn decode_batch_item<R: Row>(row: &R) -> Result<BatchItem, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> Option<String>: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> Option<f64>: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let status: String = column(row, "status")?;
+    let component_scores: Option<String> = column(row, "component_scores")?;
+    Ok(BatchItem {
+        job_id: column(row, "job_id")?,
+        position: integer_column(row, "position")?,
+        username: column(row, "username")?,
+        status: status.parse().map_err(|e| format!("status: {}", e))?,
+        final_score: column(row, "final_score")?,
+        rating: column(row, "rating")?,
+        component_scores: component_scores
+            .map(|text| serde_json::from_str(&text))
+            .transpose()
+            .map_err(|e| format!("component_scores: {}", e))?,
+        error: column(row, "error")?,
+        updated_at: timestamp_column(row, "updated_at")?,
+        claimed_at: optional_timestamp_column(row, "claimed_at")?,
+        next_attempt_at: optional_timestamp_column(row, "next_attempt_at")?,
+        attempts: integer_column(row, "attempts")?,
+    })
+}
+
+fn column<'r, R: Row, T>(row: &'r R, name: &str) -> Result<T, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    T: Decode<'r, R::Database> + Type<R::Database>,
+{
+    row.try_get(name).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn json_column<R: Row, T: DeserializeOwned>(row: &R, name: &str) -> Result<T, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let text: String = column(row, name)?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn integer_column<R: Row>(row: &R, name: &str) -> Result<u32, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let value: i64 = column(row, name)?;
+    u32::try_from(value).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn timestamp_column<R: Row>(row: &R, name: &str) -> Result<DateTime<Utc>, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let text: String = column(row, name)?;
+    parse_timestamp(&text, name)
+}
+
+fn optional_timestamp_column<R: Row>(row: &R, name: &str) -> Result<Option<DateTime<Utc>>, String>
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    for<'r> Option<String>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let text: Option<String> = column(row, name)?;
+    text.map(|text| parse_timestamp(&text, name)).transpose()
+}
+
+fn parse_timestamp(text: &str, name: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::db::models::BatchItemStatus;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn user(username: &str, last_updated: DateTime<Utc>) -> CachedUser {
+        CachedUser {
+            username: username.to_string(),
+            user_data: json!({ "login": username }),
+            repositories: vec![json!({ "name": "hello-world", "stargazers_count": 3 })],
+            events: Vec::new(),
+            pull_requests: Vec::new(),
+            last_updated,
+        }
+    }
+
+    fn score(username: &str, profile: &str, window_days: u32, total: f64) -> CachedScore {
+        CachedScore {
+            username: username.to_string(),
+            profile: profile.to_string(),
+            window_days,
+            profile_version: "v1".to_string(),
+            model_version: "1-abc".to_string(),
+            score: json!({ "total_score": total }),
+            rating: "Intermediate".to_string(),
+            stats: json!({}),
+            activity: json!({ "window_days": window_days }),
+            languages: json!({ "languages": { "Rust": 1.0 } }),
+            last_updated: Utc::now(),
+        }
+    }
+
+    fn history(username: &str, profile: &str, total: f64, computed_at: DateTime<Utc>) -> ScoreHistoryEntry {
+        ScoreHistoryEntry {
+            username: username.to_string(),
+            profile: profile.to_string(),
+            model_version: "1-abc".to_string(),
+            final_score: total,
+            component_scores: json!({ "code_quality": 0.5 }),
+            computed_at,
+        }
+    }
+
+    /// Behaviour every backend has to share.
+    pub(crate) async fn check_cache_store(store: &dyn CacheStore) {
+        let max_age = Duration::hours(1);
+
+        store.put_user(&user("octocat", Utc::now())).await.unwrap();
+        store.put_user(&user("old-timer", Utc::now() - Duration::hours(2))).await.unwrap();
+        let cached = store.get_user("octocat", max_age).await.unwrap().unwrap();
+        assert_eq!(cached.repositories[0]["stargazers_count"], 3);
+        assert!(store.get_user("old-timer", max_age).await.unwrap().is_none());
+        assert!(store.get_user("old-timer", Duration::hours(3)).await.unwrap().is_some());
+        assert!(store.get_user("nobody", max_age).await.unwrap().is_none());
+
+        store.put_score(&score("octocat", "default", 30, 40.0)).await.unwrap();
+        store.put_score(&score("octocat", "default", 30, 50.0)).await.unwrap();
+        store.put_score(&score("octocat", "default", 7, 45.0)).await.unwrap();
+        store.put_score(&score("octocat", "maintainer", 30, 60.0)).await.unwrap();
+        let cached = store.get_score("octocat", "default", 30, max_age).await.unwrap().unwrap();
+        assert_eq!(cached.score["total_score"], 50.0);
+        let cached = store.get_score("octocat", "default", 7, max_age).await.unwrap().unwrap();
+        assert_eq!((cached.window_days, cached.score["total_score"].as_f64()), (7, Some(45.0)));
+        let cached = store.get_score("octocat", "maintainer", 30, max_age).await.unwrap().unwrap();
+        assert_eq!(cached.score["total_score"], 60.0);
+        assert!(store.get_score("octocat", "maintainer", 7, max_age).await.unwrap().is_none());
+
+        let response = CachedResponse {
+            url: "https://api.github.com/users/octocat".to_string(),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            link: Some(r#"<https://api.github.com/user/1/repos?page=2>; rel="last""#.to_string()),
+            body: "{}".to_string(),
+            last_updated: Utc::now() - Duration::days(30),
+        };
+        store.put_response(&response).await.unwrap();
+        let cached = store.get_response(&response.url).await.unwrap().unwrap();
+        assert_eq!(cached.etag, response.etag);
+        assert_eq!(cached.last_modified, None);
+        assert_eq!(cached.link, response.link);
+
+        let day = |d: u32| Utc.with_ymd_and_hms(2025, 5, d, 12, 0, 0).unwrap();
+        for (d, total) in [(3, 41.0), (1, 40.0), (10, 45.0)] {
+            store.append_history(&history("octocat", "default", total, day(d))).await.unwrap();
+        }
+        store.append_history(&history("octocat", "maintainer", 70.0, day(2))).await.unwrap();
+        let series = store.get_history("octocat", "default", day(1), day(3)).await.unwrap();
+        let totals: Vec<f64> = series.iter().map(|e| e.final_score).collect();
+        assert_eq!(totals, vec![40.0, 41.0]);
+        assert_eq!(series[0].component_scores["code_quality"], 0.5);
+        assert_eq!(series[0].computed_at, day(1));
+
+        store.invalidate("octocat").await.unwrap();
+        assert_eq!(store.get_history("octocat", "default", day(1), day(31)).await.unwrap().len(), 3);
+        assert!(store.get_user("octocat", max_age).await.unwrap().is_none());
+        assert!(store.get_score("octocat", "default", 30, max_age).await.unwrap().is_none());
+        assert!(store.get_score("octocat", "default", 7, max_age).await.unwrap().is_none());
+        assert!(store.get_score("octocat", "maintainer", 30, max_age).await.unwrap().is_none());
+        assert!(store.get_user("old-timer", Duration::hours(3)).await.unwrap().is_some());
+
+        let job = |id: &str, created_at: DateTime<Utc>| BatchJob {
+            id: id.to_string(),
+            profile: "default".to_string(),
+            window_days: 30,
+            created_at,
+        };
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        store.create_batch(&job("later", day(2)), &names(&["carol", "dave"])).await.unwrap();
+        store.create_batch(&job("first", day(1)), &names(&["alice", "bob"])).await.unwrap();
+        assert_eq!(store.get_batch("first").await.unwrap().unwrap().window_days, 30);
+        assert!(store.get_batch("missing").await.unwrap().is_none());
+
+        let lease = Duration::minutes(10);
+        let alice = store.claim_batch_item(lease).await.unwrap().unwrap();
+        let bob = store.claim_batch_item(lease).await.unwrap().unwrap();
+        assert_eq!((alice.username.as_str(), bob.username.as_str()), ("alice", "bob"));
+        assert_eq!(alice.status, BatchItemStatus::Running);
+        assert_eq!(alice.attempts, 0);
+        assert!(alice.claimed_at.is_some());
+
+        // Backed off items wait until their next attempt is due
+        let back_off = |item: &BatchItem, delay: Duration| BatchItem {
+            status: BatchItemStatus::Pending,
+            claimed_at: None,
+            next_attempt_at: Some(Utc::now() + delay),
+            attempts: item.attempts + 1,
+            ..item.clone()
+        };
+        let carol = store.claim_batch_item(lease).await.unwrap().unwrap();
+        assert_eq!(carol.job_id, "later");
+        let carol = (back_off(&carol, Duration::hours(1)), carol.claimed_at.unwrap());
+        assert!(store.update_batch_item(&carol.0, carol.1).await.unwrap());
+        let dave = store.claim_batch_item(lease).await.unwrap().unwrap();
+        assert_eq!(dave.username, "dave");
+        assert!(store.update_batch_item(&back_off(&dave, Duration::seconds(-1)), dave.claimed_at.unwrap()).await.unwrap());
+        assert_eq!(store.claim_batch_item(lease).await.unwrap().unwrap().username, "dave");
+        assert!(store.claim_batch_item(lease).await.unwrap().is_none());
+        let stored = &store.get_batch_items("later").await.unwrap()[0];
+        assert_eq!(stored.status, BatchItemStatus::Pending);
+        assert_eq!(stored.next_attempt_at, carol.0.next_attempt_at);
+        assert_eq!(stored.attempts, 1);
+        // An item whose claim was handed back can't be updated under it again
+        assert!(!store.update_batch_item(&carol.0, carol.1).await.unwrap());
+
+        // Claimed items are only taken once their lease runs out, and the
+        // worker that lost the claim can no longer store a result
+        let reclaimed = store.claim_batch_item(Duration::zero()).await.unwrap().unwrap();
+        assert_eq!(reclaimed.username, "alice");
+        let mut done = alice.clone();
+        done.status = BatchItemStatus::Done;
+        done.final_score = Some(0.5);
+        done.rating = Some("Intermediate".to_string());
+        done.component_scores = Some(json!({ "code_quality": 0.25 }));
+        assert!(!store.update_batch_item(&done, alice.claimed_at.unwrap()).await.unwrap());
+        assert!(store.update_batch_item(&done, reclaimed.claimed_at.unwrap()).await.unwrap());
+        let mut failed = bob.clone();
+        failed.status = BatchItemStatus::Failed;
+        failed.error = Some("Not Found".to_string());
+        assert!(store.update_batch_item(&failed, bob.claimed_at.unwrap()).await.unwrap());
+
+        let items = store.get_batch_items("first").await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].status, BatchItemStatus::Done);
+        assert_eq!(items[0].component_scores.as_ref().unwrap()["code_quality"], 0.25);
+        assert_eq!(items[1].status, BatchItemStatus::Failed);
+        assert_eq!(items[1].final_score, None);
+        assert_eq!(items[1].error.as_deref(), Some("Not Found"));
+
+        assert_eq!(store.corrupt_rows(), 0);
+    }
+}
@@ -0,0 +1,992 @@
+use crate::db::models::CachedResponse;
+use crate::db::Database;
+use crate::scoring::{Event, GitHubUser, PullRequest, PullRequestState, Repository};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream::{self, StreamExt};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    LINK, RETRY_AFTER,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.github.com";
+
+/// Default cap on pull requests fetched through the search API.
+pub const DEFAULT_MAX_PULL_REQUESTS: usize = 300;
+
+/// The search API never returns more than 1000 results for a query.
+pub(super) const SEARCH_RESULT_LIMIT: usize = 1000;
+
+const SEARCH_PAGE_SIZE: usize = 100;
+
+/// The events API stops at 300 events, i.e. three pages of 100.
+const EVENTS_PAGE_LIMIT: u32 = 3;
+
+/// Default number of GitHub requests a single fetch keeps in flight.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// GitHub asks clients to wait at least a minute when a rate limit response
+/// carries no timing headers.
+pub(super) const DEFAULT_RATE_LIMIT_WAIT_SECS: u64 = 60;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Failed to send request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("GitHub API error: {message}")]
+    Api { status: u16, message: String },
+    #[error("GitHub rate limit exceeded, retry after {retry_after}s: {message}")]
+    RateLimited { retry_after: u64, message: String },
+    #[error("Failed to parse response: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Invalid client configuration: {0}")]
+    Config(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorBody {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    total_count: u32,
+    items: Vec<Value>,
+}
+
+/// A pull request as returned by the issue search endpoint.
+#[derive(Debug, Deserialize)]
+struct SearchIssue {
+    state: String,
+    created_at: Option<DateTime<Utc>>,
+    repository_url: String,
+    pull_request: Option<SearchPullRequestLinks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPullRequestLinks {
+    merged_at: Option<DateTime<Utc>>,
+}
+
+impl SearchIssue {
+    fn into_pull_request(self) -> PullRequest {
+        let merged_at = self.pull_request.and_then(|pr| pr.merged_at);
+        let state = match (merged_at, self.state.as_str()) {
+            (Some(_), _) => PullRequestState::Merged,
+            (None, "closed") => PullRequestState::Closed,
+            _ => PullRequestState::Open,
+        };
+        PullRequest {
+            merged_at,
+            state,
+            repository: self
+                .repository_url
+                .split_once("/repos/")
+                .map(|(_, full_name)| full_name.to_string()),
+            created_at: self.created_at,
+        }
+    }
+}
+
+pub(super) struct RawResponse {
+    pub(super) status: StatusCode,
+    pub(super) headers: HeaderMap,
+    pub(super) body: String,
+}
+
+/// Rate-limit headers returned by GitHub on a response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<DateTime<Utc>>,
+    pub retry_after: Option<u64>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+
+        Self {
+            limit: number("x-ratelimit-limit"),
+            remaining: number("x-ratelimit-remaining"),
+            reset: number("x-ratelimit-reset")
+                .and_then(|secs| Utc.timestamp_opt(secs as i64, 0).single()),
+            retry_after: number(RETRY_AFTER.as_str()),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// Whether no more than `reserve_percent` of the budget is left.
+    pub fn is_within_reserve(&self, reserve_percent: u64) -> bool {
+        match (self.remaining, self.limit) {
+            (Some(remaining), Some(limit)) => remaining * 100 <= limit * reserve_percent,
+            _ => self.is_exhausted(),
+        }
+    }
+
+    /// Seconds to wait before the next request, if the headers say so.
+    pub fn wait_seconds(&self, now: DateTime<Utc>) -> Option<u64> {
+        if let Some(retry_after) = self.retry_after {
+            return Some(retry_after);
+        }
+        match (self.is_exhausted(), self.reset) {
+            (true, Some(reset)) => Some((reset - now).num_seconds().max(0) as u64 + 1),
+            _ => None,
+        }
+    }
+}
+
+/// How secondary rate limits are retried before giving up.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Waits longer than this are returned to the caller instead of slept on.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay_for(&self, attempt: u32, rate_limit: &RateLimit) -> Duration {
+        match rate_limit.retry_after {
+            Some(secs) => Duration::from_secs(secs),
+            None => self.base_delay.saturating_mul(2u32.saturating_pow(attempt)),
+        }
+    }
+}
+
+/// Everything fetched from GitHub for a single user.
+#[derive(Debug, Clone)]
+pub struct FetchedUser {
+    pub profile: Value,
+    pub user: GitHubUser,
+}
+
+/// Which GitHub API `GitHubClient::fetch_user` reads from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataSource {
+    /// REST v3: one request per repository page, events page and PR listing.
+    #[default]
+    Rest,
+    /// GraphQL v4: a single query, including contribution totals. Requires a token.
+    GraphQl,
+}
+
+impl FromStr for DataSource {
+    type Err = FetchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rest" => Ok(Self::Rest),
+            "graphql" => Ok(Self::GraphQl),
+            other => Err(FetchError::Config(format!("unknown data source '{}'", other))),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GitHubClient {
+    pub(super) http: reqwest::Client,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    response_cache: Option<Arc<Database>>,
+    concurrency: usize,
+    data_source: DataSource,
+    max_pull_requests: usize,
+    /// Latest rate limit seen for each resource (`core`, `search`, `graphql`),
+    /// shared by clones of the client.
+    rate_limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+}
+
+impl GitHubClient {
+    pub fn new(token: Option<&str>) -> Result<Self, FetchError> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token.filter(|t| !t.is_empty()) {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| FetchError::Config(format!("invalid token: {}", e)))?;
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        let http = reqwest::Client::builder()
+            .user_agent("github-score-api")
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            retry_policy: RetryPolicy::default(),
+            response_cache: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            data_source: DataSource::default(),
+            max_pull_requests: DEFAULT_MAX_PULL_REQUESTS,
+            rate_limits: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Points the client at a different API root, e.g. GitHub Enterprise or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Stores response bodies with their ETag/Last-Modified so later requests
+    /// can be made conditional. GitHub does not count 304s against the rate limit.
+    pub fn with_response_cache(mut self, db: Arc<Database>) -> Self {
+        self.response_cache = Some(db);
+        self
+    }
+
+    /// Caps how many requests (repository pages, PR listings) run at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Caps how many authored pull requests are fetched; 0 skips them.
+    pub fn with_max_pull_requests(mut self, max_pull_requests: usize) -> Self {
+        self.max_pull_requests = max_pull_requests;
+        self
+    }
+
+    pub fn with_data_source(mut self, data_source: DataSource) -> Self {
+        self.data_source = data_source;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn max_pull_requests(&self) -> usize {
+        self.max_pull_requests
+    }
+
+    /// Seconds until every rate limit seen so far has more than
+    /// `reserve_percent` of its budget left, or `None` if they all do now.
+    /// Budgets past their reset time count as full again.
+    pub fn rate_limit_wait(&self, reserve_percent: u64, now: DateTime<Utc>) -> Option<u64> {
+        let rate_limits = self.rate_limits.lock().unwrap_or_else(|e| e.into_inner());
+        rate_limits
+            .values()
+            .filter(|rate_limit| rate_limit.is_within_reserve(reserve_percent))
+            .filter_map(|rate_limit| rate_limit.reset.filter(|reset| *reset > now))
+            .map(|reset| (reset - now).num_seconds().max(0) as u64 + 1)
+            .max()
+    }
+
+    fn record_rate_limit(&self, headers: &HeaderMap) {
+        let rate_limit = RateLimit::from_headers(headers);
+        if rate_limit.remaining.is_none() {
+            return;
+        }
+        let resource = header_string(headers, reqwest::header::HeaderName::from_static("x-ratelimit-resource"))
+            .unwrap_or_else(|| "core".to_string());
+        self.rate_limits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(resource, rate_limit);
+    }
+
+    pub async fn get_user(&self, username: &str) -> Result<Value, FetchError> {
+        self.get_json(&format!("/users/{}", username)).await
+    }
+
+    /// Fetches the first repository page, then the remaining pages named by
+    /// its `Link` header in parallel.
+    pub async fn get_repositories(&self, username: &str) -> Result<Vec<Repository>, FetchError> {
+        let repos_path = |page: u32| format!("/users/{}/repos?per_page=100&page={}", username, page);
+
+        let (mut all_repos, link): (Vec<Value>, _) = self.get_json_with_link(&repos_path(1)).await?;
+        println!("Successfully fetched {} repositories from page 1", all_repos.len());
+
+        let last_page = link.as_deref().and_then(last_page).unwrap_or(1);
+        // Collected up front: streaming over a lazy `map` closure makes the
+        // handler future fail the `Send` check axum requires.
+        let requests: Vec<_> = (2..=last_page)
+            .map(|page| self.get_owned::<Vec<Value>>(repos_path(page)))
+            .collect();
+        let pages: Vec<Vec<Value>> = stream::iter(requests)
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        for (page, repos) in (2..).zip(pages) {
+            println!("Successfully fetched {} repositories from page {}", repos.len(), page);
+            all_repos.extend(repos);
+        }
+        println!("Total repositories fetched: {}", all_repos.len());
+
+        Ok(parse_lenient(all_repos, "repository"))
+    }
+
+    /// Pages through the events API, which serves at most 300 events from
+    /// the last 90 days.
+    pub async fn get_events(&self, username: &str) -> Result<Vec<Event>, FetchError> {
+        let events_path = |page: u32| format!("/users/{}/events?per_page=100&page={}", username, page);
+
+        let (mut events, link): (Vec<Value>, _) = self.get_json_with_link(&events_path(1)).await?;
+        let last_page = link
+            .as_deref()
+            .and_then(last_page)
+            .unwrap_or(1)
+            .min(EVENTS_PAGE_LIMIT);
+
+        let requests: Vec<_> = (2..=last_page)
+            .map(|page| self.get_owned::<Vec<Value>>(events_path(page)))
+            .collect();
+        let pages: Vec<Vec<Value>> = stream::iter(requests)
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        for page in pages {
+            events.extend(page);
+        }
+        println!("Successfully fetched {} events", events.len());
+
+        Ok(parse_lenient(events, "event"))
+    }
+
+    /// Searches for every pull request `username` authored anywhere on
+    /// GitHub, newest first, up to the configured cap.
+    pub async fn get_pull_requests(&self, username: &str) -> Result<Vec<PullRequest>, FetchError> {
+        if self.max_pull_requests == 0 {
+            return Ok(Vec::new());
+        }
+        let search_path = |page: usize| {
+            format!(
+                "/search/issues?q=author:{}+type:pr&sort=created&order=desc&per_page={}&page={}",
+                username, SEARCH_PAGE_SIZE, page
+            )
+        };
+
+        let first: SearchResults = self.get_json(&search_path(1)).await?;
+        let wanted = (first.total_count as usize)
+            .min(self.max_pull_requests)
+            .min(SEARCH_RESULT_LIMIT);
+        let last_page = wanted.div_ceil(SEARCH_PAGE_SIZE).max(1);
+
+        let requests: Vec<_> = (2..=last_page)
+            .map(|page| self.get_owned::<SearchResults>(search_path(page)))
+            .collect();
+        let pages: Vec<SearchResults> = stream::iter(requests)
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+
+        let mut items = first.items;
+        for page in pages {
+            items.extend(page.items);
+        }
+        items.truncate(self.max_pull_requests);
+        println!("Successfully fetched {} PRs authored by {}", items.len(), username);
+
+        Ok(parse_lenient::<SearchIssue>(items, "pull request")
+            .into_iter()
+            .map(SearchIssue::into_pull_request)
+            .collect())
+    }
+
+    /// Fetches the profile, repositories, events and pull requests for
+    /// `username` from the configured data source.
+    pub async fn fetch_user(&self, username: &str) -> Result<FetchedUser, FetchError> {
+        match self.data_source {
+            DataSource::Rest => self.fetch_user_rest(username).await,
+            DataSource::GraphQl => self.fetch_user_graphql(username).await,
+        }
+    }
+
+    /// REST version of `fetch_user`. The profile, repositories, events and
+    /// pull requests are requested concurrently. A failed PR search fails the
+    /// fetch, as scoring without the PRs would understate the user.
+    pub async fn fetch_user_rest(&self, username: &str) -> Result<FetchedUser, FetchError> {
+        let (profile, repositories, events, pull_requests) = futures::try_join!(
+            self.get_user(username),
+            self.get_repositories(username),
+            self.get_events(username),
+            self.get_pull_requests(username),
+        )?;
+
+        Ok(FetchedUser {
+            profile,
+            user: GitHubUser {
+                login: username.to_string(),
+                repositories,
+                events,
+                pull_requests,
+                contributions: None,
+            },
+        })
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, FetchError> {
+        Ok(self.get_json_with_link(path).await?.0)
+    }
+
+    /// `get_json` taking an owned path, so the future borrows nothing but `self`.
+    async fn get_owned<T: DeserializeOwned>(&self, path: String) -> Result<T, FetchError> {
+        self.get_json(&path).await
+    }
+
+    /// Like `get_json`, but also returns the `Link` header used for pagination.
+    async fn get_json_with_link<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<(T, Option<String>), FetchError> {
+        let url = format!("{}{}", self.base_url, path);
+        let cached = self.cached_response(&url).await;
+
+        let response = self
+            .send(&url, || {
+                let mut request = self.http.get(&url);
+                if let Some(cached) = &cached {
+                    if let Some(etag) = &cached.etag {
+                        request = request.header(IF_NONE_MATCH, etag);
+                    }
+                    if let Some(last_modified) = &cached.last_modified {
+                        request = request.header(IF_MODIFIED_SINCE, last_modified);
+                    }
+                }
+                request
+            })
+            .await?;
+        let link = header_string(&response.headers, LINK);
+
+        if response.status == StatusCode::NOT_MODIFIED {
+            return match cached {
+                Some(cached) => {
+                    println!("Not modified, reusing cached response for {}", url);
+                    // GitHub does not reliably repeat pagination links on a 304
+                    Ok((serde_json::from_str(&cached.body)?, link.or(cached.link)))
+                }
+                None => Err(FetchError::Api {
+                    status: response.status.as_u16(),
+                    message: "Not Modified without a cached response".to_string(),
+                }),
+            };
+        }
+
+        let value = serde_json::from_str(&response.body)?;
+        let etag = header_string(&response.headers, ETAG);
+        let last_modified = header_string(&response.headers, LAST_MODIFIED);
+        if etag.is_some() || last_modified.is_some() {
+            self.store_response(CachedResponse {
+                url,
+                etag,
+                last_modified,
+                link: link.clone(),
+                body: response.body,
+                last_updated: Utc::now(),
+            })
+            .await;
+        }
+        Ok((value, link))
+    }
+
+    /// Sends the request built by `build`, retrying secondary rate limits.
+    /// Successful and `304 Not Modified` responses are returned as-is; every
+    /// other status becomes a `FetchError`.
+    pub(super) async fn send(
+        &self,
+        url: &str,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<RawResponse, FetchError> {
+        let mut attempt = 0;
+        loop {
+            println!("Fetching {}", url);
+
+            let response = build().send().await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.text().await?;
+            self.record_rate_limit(&headers);
+
+            if status.is_success() || status == StatusCode::NOT_MODIFIED {
+                return Ok(RawResponse { status, headers, body });
+            }
+
+            let rate_limit = RateLimit::from_headers(&headers);
+            let message = serde_json::from_str::<ApiErrorBody>(&body)
+                .map(|body| body.message)
+                .unwrap_or(body);
+
+            if !is_rate_limited(status, &rate_limit, &message) {
+                return Err(FetchError::Api {
+                    status: status.as_u16(),
+                    message,
+                });
+            }
+
+            // Secondary limits clear after a short pause; an exhausted primary
+            // budget does not come back until the reset time.
+            if !rate_limit.is_exhausted() && attempt < self.retry_policy.max_retries {
+                let delay = self.retry_policy.delay_for(attempt, &rate_limit);
+                if delay <= self.retry_policy.max_delay {
+                    println!("Secondary rate limit hit, retrying in {:?}", delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            return Err(FetchError::RateLimited {
+                retry_after: rate_limit
+                    .wait_seconds(Utc::now())
+                    .unwrap_or(DEFAULT_RATE_LIMIT_WAIT_SECS),
+                message,
+            });
+        }
+    }
+
+    async fn cached_response(&self, url: &str) -> Option<CachedResponse> {
+        let db = self.response_cache.as_ref()?;
+        match db.get_cached_response(url).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                println!("Failed to read cached response for {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    async fn store_response(&self, response: CachedResponse) {
+        if let Some(db) = &self.response_cache {
+            if let Err(e) = db.cache_response(&response).await {
+                println!("Failed to cache response for {}: {}", response.url, e);
+            }
+        }
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Extracts the `page` of the `rel="last"` entry in a GitHub `Link` header.
+fn last_page(link: &str) -> Option<u32> {
+    link.split(',')
+        .find(|part| part.contains(r#"rel="last""#))
+        .and_then(|part| {
+            let url = part.split(';').next()?.trim().trim_start_matches('<').trim_end_matches('>');
+            let query = url.split_once('?')?.1;
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("page="))
+                .and_then(|page| page.parse().ok())
+        })
+}
+
+fn is_rate_limited(status: StatusCode, rate_limit: &RateLimit, message: &str) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (rate_limit.is_exhausted()
+                || rate_limit.retry_after.is_some()
+                || message.to_lowercase().contains("rate limit")))
+}
+
+/// Deserializes each value on its own, dropping (and logging) the ones that don't fit `T`.
+pub fn parse_lenient<T: DeserializeOwned>(values: Vec<Value>, kind: &str) -> Vec<T> {
+    values
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value(value) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                println!("Failed to parse {}: {}", kind, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::Query,
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    async fn spawn_mock(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn repo(name: &str) -> Value {
+        json!({
+            "name": name,
+            "full_name": format!("octo/{}", name),
+            "stargazers_count": 3,
+            "forks_count": 1,
+            "updated_at": "2024-01-01T00:00:00Z",
+            "owner": { "login": "octo" },
+            "description": null,
+            "language": "Rust"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_user_against_mock() {
+        let router = Router::new()
+            .route("/users/octo", get(|| async { Json(json!({ "login": "octo" })) }))
+            .route(
+                "/users/octo/repos",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    if params.get("page").map(String::as_str) == Some("1") {
+                        Json(json!([repo("a"), repo("b")]))
+                    } else {
+                        Json(json!([]))
+                    }
+                }),
+            )
+            .route(
+                "/users/octo/events",
+                get(|| async {
+                    Json(json!([{
+                        "type": "PushEvent",
+                        "created_at": "2024-01-02T00:00:00Z",
+                        "repo": { "name": "octo/a" },
+                        "payload": {}
+                    }]))
+                }),
+            )
+            .route(
+                "/search/issues",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    assert_eq!(params["q"], "author:octo type:pr");
+                    Json(json!({
+                        "total_count": 2,
+                        "items": [
+                            {
+                                "state": "closed",
+                                "created_at": "2024-01-03T00:00:00Z",
+                                "repository_url": "https://api.github.com/repos/rust-lang/rust",
+                                "pull_request": { "merged_at": "2024-01-04T00:00:00Z" }
+                            },
+                            {
+                                "state": "open",
+                                "created_at": "2024-01-05T00:00:00Z",
+                                "repository_url": "https://api.github.com/repos/octo/a",
+                                "pull_request": { "merged_at": null }
+                            }
+                        ]
+                    }))
+                }),
+            );
+        let base_url = spawn_mock(router).await;
+        let client = GitHubClient::new(None).unwrap().with_base_url(format!("{}/", base_url));
+
+        let fetched = client.fetch_user("octo").await.unwrap();
+        assert_eq!(fetched.profile["login"], "octo");
+        assert_eq!(fetched.user.repositories.len(), 2);
+        assert_eq!(fetched.user.repositories[0].language.as_deref(), Some("Rust"));
+        assert_eq!(fetched.user.events.len(), 1);
+        let pulls = &fetched.user.pull_requests;
+        assert_eq!(pulls.len(), 2);
+        assert_eq!(pulls[0].state, PullRequestState::Merged);
+        assert_eq!(pulls[0].repository.as_deref(), Some("rust-lang/rust"));
+        assert!(pulls[0].is_external("octo"));
+        assert_eq!(pulls[1].state, PullRequestState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_failed_pull_request_search_fails_the_fetch() {
+        let router = Router::new()
+            .route("/users/octo", get(|| async { Json(json!({ "login": "octo" })) }))
+            .route("/users/octo/repos", get(|| async { Json(json!([])) }))
+            .route("/users/octo/events", get(|| async { Json(json!([])) }))
+            .route(
+                "/search/issues",
+                get(|| async { (StatusCode::BAD_GATEWAY, Json(json!({ "message": "Server Error" }))) }),
+            );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+
+        match client.fetch_user("octo").await {
+            Err(FetchError::Api { status, .. }) => assert_eq!(status, 502),
+            other => panic!("expected API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_error_keeps_status_and_message() {
+        let router = Router::new().route(
+            "/users/ghost",
+            get(|| async { (StatusCode::NOT_FOUND, Json(json!({ "message": "Not Found" }))) }),
+        );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+
+        match client.get_user("ghost").await {
+            Err(FetchError::Api { status, message }) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "Not Found");
+            }
+            other => panic!("expected API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secondary_rate_limit_is_retried() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let router = Router::new().route(
+            "/users/octo",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        (
+                            StatusCode::FORBIDDEN,
+                            [("retry-after", "0")],
+                            Json(json!({ "message": "You have exceeded a secondary rate limit." })),
+                        )
+                            .into_response()
+                    } else {
+                        Json(json!({ "login": "octo" })).into_response()
+                    }
+                }
+            }),
+        );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+
+        let profile = client.get_user("octo").await.unwrap();
+        assert_eq!(profile["login"], "octo");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_rate_limit_reports_retry_after() {
+        let reset = (Utc::now().timestamp() + 120).to_string();
+        let router = Router::new().route(
+            "/users/octo",
+            get(move || async move {
+                (
+                    StatusCode::FORBIDDEN,
+                    [("x-ratelimit-remaining", "0".to_string()), ("x-ratelimit-reset", reset)],
+                    Json(json!({ "message": "API rate limit exceeded" })),
+                )
+            }),
+        );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+
+        match client.get_user("octo").await {
+            Err(FetchError::RateLimited { retry_after, .. }) => {
+                assert!((100..=122).contains(&retry_after), "retry_after = {}", retry_after);
+            }
+            other => panic!("expected rate limit error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_wait_keeps_a_reserve() {
+        let reset = (Utc::now().timestamp() + 120).to_string();
+        let router = Router::new().route(
+            "/users/octo",
+            get(move || async move {
+                (
+                    [
+                        ("x-ratelimit-limit", "60".to_string()),
+                        ("x-ratelimit-remaining", "5".to_string()),
+                        ("x-ratelimit-reset", reset),
+                        ("x-ratelimit-resource", "core".to_string()),
+                    ],
+                    Json(json!({ "login": "octo" })),
+                )
+            }),
+        );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+        let now = Utc::now();
+        assert_eq!(client.rate_limit_wait(10, now), None);
+
+        client.get_user("octo").await.unwrap();
+        let wait = client.rate_limit_wait(10, now).unwrap();
+        assert!((100..=122).contains(&wait), "wait = {}", wait);
+        assert_eq!(client.clone().rate_limit_wait(5, now), None);
+        assert_eq!(client.rate_limit_wait(10, now + chrono::Duration::minutes(3)), None);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_reuses_cached_body() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let router = Router::new().route(
+            "/users/octo",
+            get(move |headers: axum::http::HeaderMap| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if headers.get("if-none-match").is_some_and(|v| v == "\"v1\"") {
+                        StatusCode::NOT_MODIFIED.into_response()
+                    } else {
+                        ([("etag", "\"v1\"")], Json(json!({ "login": "octo" }))).into_response()
+                    }
+                }
+            }),
+        );
+        let db = Arc::new(Database::in_memory().await.unwrap());
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_base_url(spawn_mock(router).await)
+            .with_response_cache(db.clone());
+
+        let first = client.get_user("octo").await.unwrap();
+        let second = client.get_user("octo").await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(second["login"], "octo");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let stored = db
+            .get_cached_response(&format!("{}/users/octo", client.base_url()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_without_link_still_fetches_every_page() {
+        let router = Router::new().route(
+            "/users/octo/repos",
+            get(|Query(params): Query<HashMap<String, String>>, headers: axum::http::HeaderMap| async move {
+                let page: u32 = params["page"].parse().unwrap();
+                if page == 1 && headers.get("if-none-match").is_some_and(|v| v == "\"p1\"") {
+                    // No Link header on the 304
+                    return StatusCode::NOT_MODIFIED.into_response();
+                }
+                let repos = Json(json!([repo(&format!("r{}", page))]));
+                if page == 1 {
+                    let link = r#"</users/octo/repos?per_page=100&page=3>; rel="last""#;
+                    ([("etag", "\"p1\""), ("link", link)], repos).into_response()
+                } else {
+                    repos.into_response()
+                }
+            }),
+        );
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_base_url(spawn_mock(router).await)
+            .with_response_cache(Arc::new(Database::in_memory().await.unwrap()));
+
+        let first = client.get_repositories("octo").await.unwrap();
+        let revalidated = client.get_repositories("octo").await.unwrap();
+        assert_eq!(first.len(), 3);
+        let names: Vec<&str> = revalidated.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["r1", "r2", "r3"]);
+    }
+
+    #[test]
+    fn test_last_page_from_link_header() {
+        let link = r#"<https://api.github.com/user/1/repos?per_page=100&page=2>; rel="next", <https://api.github.com/user/1/repos?per_page=100&page=6>; rel="last""#;
+        assert_eq!(last_page(link), Some(6));
+        assert_eq!(last_page(r#"<https://api.github.com/x?page=1>; rel="prev""#), None);
+    }
+
+    #[tokio::test]
+    async fn test_repository_pages_fetched_from_link_header() {
+        let router = Router::new().route(
+            "/users/octo/repos",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let page: u32 = params["page"].parse().unwrap();
+                let repos = Json(json!([repo(&format!("r{}a", page)), repo(&format!("r{}b", page))]));
+                if page == 1 {
+                    let link = r#"</users/octo/repos?per_page=100&page=2>; rel="next", </users/octo/repos?per_page=100&page=3>; rel="last""#;
+                    ([("link", link)], repos).into_response()
+                } else {
+                    repos.into_response()
+                }
+            }),
+        );
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_base_url(spawn_mock(router).await)
+            .with_concurrency(2);
+
+        let names: Vec<_> = client
+            .get_repositories("octo")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(names, ["r1a", "r1b", "r2a", "r2b", "r3a", "r3b"]);
+    }
+
+    #[tokio::test]
+    async fn test_events_stop_at_api_page_limit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let router = Router::new().route(
+            "/users/octo/events",
+            get(move |Query(params): Query<HashMap<String, String>>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let event = json!({
+                        "type": "PushEvent",
+                        "created_at": "2024-01-02T00:00:00Z",
+                        "repo": { "name": "octo/a" },
+                        "payload": {}
+                    });
+                    let events = Json(json!([event, event]));
+                    if params["page"] == "1" {
+                        let link = r#"</users/octo/events?page=2>; rel="next", </users/octo/events?page=5>; rel="last""#;
+                        ([("link", link)], events).into_response()
+                    } else {
+                        events.into_response()
+                    }
+                }
+            }),
+        );
+        let client = GitHubClient::new(None).unwrap().with_base_url(spawn_mock(router).await);
+
+        let events = client.get_events("octo").await.unwrap();
+        assert_eq!(events.len(), 6);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,678 @@
+use super::client::{
+    FetchError, FetchedUser, GitHubClient, RateLimit, DEFAULT_RATE_LIMIT_WAIT_SECS, SEARCH_RESULT_LIMIT,
+};
+use crate::scoring::{
+    ContributionTotals, Event, EventPayload, GitHubUser, PullRequest, PullRequestState, Repo,
+    Repository, User,
+};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Connections are fetched this many nodes at a time, the GraphQL maximum.
+const PAGE_SIZE: usize = 100;
+
+/// Everything the scorer needs, with the first page of repositories and pull
+/// requests. Contributions cover the default `contributionsCollection`
+/// window, the last year.
+const USER_QUERY: &str = r#"
+query($login: String!, $pullRequests: Int!) {
+  user(login: $login) {
+    login
+    name
+    bio
+    createdAt
+    followers { totalCount }
+    repositories(first: 100, ownerAffiliations: OWNER, orderBy: {field: PUSHED_AT, direction: DESC}) {
+      totalCount
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        name
+        nameWithOwner
+        description
+        stargazerCount
+        forkCount
+        pushedAt
+        updatedAt
+        owner { login }
+        primaryLanguage { name }
+      }
+    }
+    pullRequests(first: $pullRequests, orderBy: {field: CREATED_AT, direction: DESC}) {
+      totalCount
+      pageInfo { hasNextPage endCursor }
+      nodes { createdAt mergedAt state repository { nameWithOwner } }
+    }
+    contributionsCollection {
+      totalCommitContributions
+      totalIssueContributions
+      totalPullRequestContributions
+      totalPullRequestReviewContributions
+      commitContributionsByRepository(maxRepositories: 25) {
+        repository { nameWithOwner }
+        contributions(first: 100) { nodes { occurredAt commitCount } }
+      }
+      issueContributions(first: 100) {
+        nodes { occurredAt issue { state repository { nameWithOwner } } }
+      }
+      pullRequestContributions(first: 100) {
+        nodes { occurredAt pullRequest { repository { nameWithOwner } } }
+      }
+      pullRequestReviewContributions(first: 100) {
+        nodes { occurredAt pullRequestReview { repository { nameWithOwner } } }
+      }
+    }
+  }
+}
+"#;
+
+/// The repositories after `$after`, aliased to `page`.
+const REPOSITORIES_QUERY: &str = r#"
+query($login: String!, $first: Int!, $after: String) {
+  user(login: $login) {
+    page: repositories(first: $first, after: $after, ownerAffiliations: OWNER, orderBy: {field: PUSHED_AT, direction: DESC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        name
+        nameWithOwner
+        description
+        stargazerCount
+        forkCount
+        pushedAt
+        updatedAt
+        owner { login }
+        primaryLanguage { name }
+      }
+    }
+  }
+}
+"#;
+
+/// The pull requests after `$after`, aliased to `page`.
+const PULL_REQUESTS_QUERY: &str = r#"
+query($login: String!, $first: Int!, $after: String) {
+  user(login: $login) {
+    page: pullRequests(first: $first, after: $after, orderBy: {field: CREATED_AT, direction: DESC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes { createdAt mergedAt state repository { nameWithOwner } }
+    }
+  }
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlError {
+    message: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserData<T> {
+    user: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageNode<T> {
+    page: Connection<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserNode {
+    login: String,
+    name: Option<String>,
+    bio: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    followers: Connection<Empty>,
+    repositories: Connection<RepositoryNode>,
+    pull_requests: Connection<PullRequestNode>,
+    contributions_collection: ContributionsCollection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    #[serde(default)]
+    total_count: u32,
+    #[serde(default)]
+    page_info: PageInfo,
+    #[serde(default = "Vec::new")]
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Empty {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryNode {
+    name: String,
+    name_with_owner: String,
+    description: Option<String>,
+    stargazer_count: u32,
+    fork_count: u32,
+    pushed_at: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+    owner: Login,
+    primary_language: Option<Named>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Login {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestNode {
+    created_at: Option<DateTime<Utc>>,
+    merged_at: Option<DateTime<Utc>>,
+    state: String,
+    repository: RepositoryName,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionsCollection {
+    total_commit_contributions: u32,
+    total_issue_contributions: u32,
+    total_pull_request_contributions: u32,
+    total_pull_request_review_contributions: u32,
+    commit_contributions_by_repository: Vec<CommitContributions>,
+    issue_contributions: Connection<IssueContribution>,
+    pull_request_contributions: Connection<PullRequestContribution>,
+    pull_request_review_contributions: Connection<ReviewContribution>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitContributions {
+    repository: RepositoryName,
+    contributions: Connection<CommitContribution>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryName {
+    name_with_owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitContribution {
+    occurred_at: DateTime<Utc>,
+    commit_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssueContribution {
+    occurred_at: DateTime<Utc>,
+    issue: IssueNode,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueNode {
+    state: String,
+    repository: RepositoryName,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestContribution {
+    occurred_at: DateTime<Utc>,
+    pull_request: RepositoryOf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewContribution {
+    occurred_at: DateTime<Utc>,
+    pull_request_review: RepositoryOf,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryOf {
+    repository: RepositoryName,
+}
+
+impl GitHubClient {
+    /// Fetches the same data as the REST path: every repository and, like
+    /// the search API, pull requests up to the configured cap. Contributions
+    /// are turned into synthetic events so the scorer sees the same shapes it
+    /// gets from `/users/{user}/events`.
+    pub async fn fetch_user_graphql(&self, username: &str) -> Result<FetchedUser, FetchError> {
+        let max_pull_requests = self.max_pull_requests().min(SEARCH_RESULT_LIMIT);
+        let variables = json!({ "login": username, "pullRequests": max_pull_requests.clamp(1, PAGE_SIZE) });
+        let data: UserData<UserNode> = self.graphql(USER_QUERY, variables).await?;
+        let mut user = data.user.ok_or_else(|| not_found(username))?;
+
+        self.next_pages(REPOSITORIES_QUERY, username, &mut user.repositories, usize::MAX)
+            .await?;
+        self.next_pages(PULL_REQUESTS_QUERY, username, &mut user.pull_requests, max_pull_requests)
+            .await?;
+        user.pull_requests.nodes.truncate(max_pull_requests);
+        println!(
+            "Successfully fetched {} repositories and {} PRs via GraphQL",
+            user.repositories.nodes.len(),
+            user.pull_requests.nodes.len()
+        );
+
+        Ok(user.into_fetched())
+    }
+
+    /// Follows `connection`'s cursor with `query` until there are no more
+    /// pages or it holds `cap` nodes.
+    async fn next_pages<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        username: &str,
+        connection: &mut Connection<T>,
+        cap: usize,
+    ) -> Result<(), FetchError> {
+        while connection.page_info.has_next_page && connection.nodes.len() < cap {
+            let variables = json!({
+                "login": username,
+                "first": (cap - connection.nodes.len()).min(PAGE_SIZE),
+                "after": connection.page_info.end_cursor,
+            });
+            let data: UserData<PageNode<T>> = self.graphql(query, variables).await?;
+            let page = data.user.ok_or_else(|| not_found(username))?.page;
+            connection.nodes.extend(page.nodes);
+            connection.page_info = page.page_info;
+        }
+        Ok(())
+    }
+
+    /// Runs one query and maps GraphQL errors onto the REST path's errors.
+    async fn graphql<T: DeserializeOwned>(&self, query: &str, variables: Value) -> Result<T, FetchError> {
+        let url = self.graphql_url();
+        let body = json!({ "query": query, "variables": variables });
+
+        let response = self.send(&url, || self.http.post(&url).json(&body)).await?;
+        let parsed: GraphQlResponse<T> = serde_json::from_str(&response.body)?;
+
+        if let Some(error) = parsed.errors.first() {
+            let message = parsed
+                .errors
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(match error.kind.as_deref() {
+                Some("NOT_FOUND") => FetchError::Api { status: 404, message },
+                Some("RATE_LIMITED") => FetchError::RateLimited {
+                    retry_after: RateLimit::from_headers(&response.headers)
+                        .wait_seconds(Utc::now())
+                        .unwrap_or(DEFAULT_RATE_LIMIT_WAIT_SECS),
+                    message,
+                },
+                _ => FetchError::Api { status: 502, message },
+            });
+        }
+
+        parsed.data.ok_or_else(|| FetchError::Api {
+            status: 502,
+            message: "GraphQL response had neither data nor errors".to_string(),
+        })
+    }
+
+    /// `https://api.github.com/graphql`, or `/api/graphql` for Enterprise
+    /// servers whose REST root ends in `/v3`.
+    fn graphql_url(&self) -> String {
+        match self.base_url().strip_suffix("/v3") {
+            Some(root) => format!("{}/graphql", root),
+            None => format!("{}/graphql", self.base_url()),
+        }
+    }
+}
+
+impl UserNode {
+    fn into_fetched(self) -> FetchedUser {
+        let collection = self.contributions_collection;
+        let contributions = ContributionTotals {
+            commits: collection.total_commit_contributions,
+            issues: collection.total_issue_contributions,
+            pull_requests: collection.total_pull_request_contributions,
+            reviews: collection.total_pull_request_review_contributions,
+        };
+
+        let profile = json!({
+            "login": self.login,
+            "name": self.name,
+            "bio": self.bio,
+            "created_at": self.created_at,
+            "followers": self.followers.total_count,
+            "public_repos": self.repositories.total_count,
+            "contributions": contributions,
+        });
+
+        let repositories = self
+            .repositories
+            .nodes
+            .into_iter()
+            .map(|r| Repository {
+                name: r.name,
+                full_name: r.name_with_owner,
+                stargazers_count: r.stargazer_count,
+                forks_count: r.fork_count,
+                updated_at: r.pushed_at.unwrap_or(r.updated_at),
+                owner: User { login: r.owner.login },
+                description: r.description,
+                language: r.primary_language.map(|l| l.name),
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        for by_repo in collection.commit_contributions_by_repository {
+            for commit in by_repo.contributions.nodes {
+                let mut event =
+                    synthetic_event("PushEvent", commit.occurred_at, &by_repo.repository, None);
+                // Contribution days carry a commit count but not the commits
+                if let Some(payload) = event.payload.as_mut() {
+                    payload.size = Some(commit.commit_count);
+                    payload.distinct_size = Some(commit.commit_count);
+                }
+                events.push(event);
+            }
+        }
+        for c in collection.issue_contributions.nodes {
+            let action = if c.issue.state == "CLOSED" { "closed" } else { "opened" };
+            events.push(synthetic_event("IssuesEvent", c.occurred_at, &c.issue.repository, Some(action)));
+        }
+        for c in collection.pull_request_contributions.nodes {
+            events.push(synthetic_event(
+                "PullRequestEvent",
+                c.occurred_at,
+                &c.pull_request.repository,
+                Some("opened"),
+            ));
+        }
+        for c in collection.pull_request_review_contributions.nodes {
+            events.push(synthetic_event(
+                "PullRequestReviewEvent",
+                c.occurred_at,
+                &c.pull_request_review.repository,
+                Some("created"),
+            ));
+        }
+        events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+
+        let pull_requests = self
+            .pull_requests
+            .nodes
+            .into_iter()
+            .map(|pr| PullRequest {
+                merged_at: pr.merged_at,
+                state: match pr.state.as_str() {
+                    "MERGED" => PullRequestState::Merged,
+                    "CLOSED" => PullRequestState::Closed,
+                    _ => PullRequestState::Open,
+                },
+                repository: Some(pr.repository.name_with_owner),
+                created_at: pr.created_at,
+            })
+            .collect();
+
+        FetchedUser {
+            profile,
+            user: GitHubUser {
+                login: self.login,
+                repositories,
+                events,
+                pull_requests,
+                contributions: Some(contributions),
+            },
+        }
+    }
+}
+
+fn not_found(username: &str) -> FetchError {
+    FetchError::Api {
+        status: 404,
+        message: format!("Could not resolve to a User with the login of '{}'", username),
+    }
+}
+
+fn synthetic_event(
+    kind: &str,
+    created_at: DateTime<Utc>,
+    repository: &RepositoryName,
+    action: Option<&str>,
+) -> Event {
+    Event {
+        r#type: kind.to_string(),
+        created_at,
+        repo: Some(Repo {
+            name: repository.name_with_owner.clone(),
+        }),
+        payload: Some(EventPayload {
+            action: action.map(str::to_string),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::{Commit, GitHubScorer, ScoringConfig};
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+
+    async fn spawn_stub(response: Value) -> String {
+        spawn_handler(move |_| response.clone()).await
+    }
+
+    /// Serves whatever `respond` returns for each request's body.
+    async fn spawn_handler(respond: impl Fn(&Value) -> Value + Clone + Send + Sync + 'static) -> String {
+        let router = Router::new().route(
+            "/graphql",
+            post(move |Json(request): Json<Value>| async move {
+                assert_eq!(request["variables"]["login"], "octo");
+                Json(respond(&request))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn canned_user() -> Value {
+        json!({ "data": { "user": {
+            "login": "octo",
+            "name": "Octo Cat",
+            "bio": null,
+            "createdAt": "2015-01-01T00:00:00Z",
+            "followers": { "totalCount": 12 },
+            "repositories": { "totalCount": 1, "nodes": [{
+                "name": "spoon",
+                "nameWithOwner": "octo/spoon",
+                "description": "A spoon",
+                "stargazerCount": 40,
+                "forkCount": 4,
+                "pushedAt": "2024-03-01T00:00:00Z",
+                "updatedAt": "2024-02-01T00:00:00Z",
+                "owner": { "login": "octo" },
+                "primaryLanguage": { "name": "Rust" }
+            }]},
+            "pullRequests": { "totalCount": 2, "nodes": [
+                { "createdAt": "2024-03-01T00:00:00Z", "mergedAt": "2024-03-02T00:00:00Z",
+                  "state": "MERGED", "repository": { "nameWithOwner": "rust-lang/rust" } },
+                { "createdAt": "2024-03-04T00:00:00Z", "mergedAt": null,
+                  "state": "OPEN", "repository": { "nameWithOwner": "octo/spoon" } }
+            ]},
+            "contributionsCollection": {
+                "totalCommitContributions": 30,
+                "totalIssueContributions": 1,
+                "totalPullRequestContributions": 2,
+                "totalPullRequestReviewContributions": 5,
+                "commitContributionsByRepository": [{
+                    "repository": { "nameWithOwner": "octo/spoon" },
+                    "contributions": { "nodes": [
+                        { "occurredAt": "2024-03-01T00:00:00Z", "commitCount": 20 },
+                        { "occurredAt": "2024-02-01T00:00:00Z", "commitCount": 10 }
+                    ]}
+                }],
+                "issueContributions": { "nodes": [
+                    { "occurredAt": "2024-01-01T00:00:00Z",
+                      "issue": { "state": "CLOSED", "repository": { "nameWithOwner": "rust-lang/rust" } } }
+                ]},
+                "pullRequestContributions": { "nodes": [] },
+                "pullRequestReviewContributions": { "nodes": [
+                    { "occurredAt": "2024-03-03T00:00:00Z",
+                      "pullRequestReview": { "repository": { "nameWithOwner": "tokio-rs/tokio" } } }
+                ]}
+            }
+        }}})
+    }
+
+    #[tokio::test]
+    async fn test_graphql_fills_github_user() {
+        let client = GitHubClient::new(Some("token"))
+            .unwrap()
+            .with_base_url(spawn_stub(canned_user()).await);
+
+        let fetched = client.fetch_user_graphql("octo").await.unwrap();
+        let user = fetched.user;
+        assert_eq!(user.repositories.len(), 1);
+        assert_eq!(user.repositories[0].full_name, "octo/spoon");
+        assert_eq!(user.repositories[0].language.as_deref(), Some("Rust"));
+        assert_eq!(user.repositories[0].updated_at.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(user.pull_requests.iter().filter(|pr| pr.is_merged()).count(), 1);
+        assert!(user.pull_requests[0].is_external("octo"));
+        assert_eq!(user.events.len(), 4);
+        assert_eq!(user.events[0].r#type, "PullRequestReviewEvent");
+        let commits: usize = user.events.iter().map(|e| e.commit_count(false)).sum();
+        assert_eq!(commits, 30);
+        assert_eq!(user.contributions.as_ref().map(|c| c.total()), Some(38));
+        assert_eq!(fetched.profile["public_repos"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_commit_messages_do_not_lower_code_quality() {
+        let client = GitHubClient::new(Some("token"))
+            .unwrap()
+            .with_base_url(spawn_stub(canned_user()).await);
+        let graphql = client.fetch_user_graphql("octo").await.unwrap().user;
+        // The same user as the REST source sees them, with each push's commits
+        let mut rest = graphql.clone();
+        rest.contributions = None;
+        for payload in rest.events.iter_mut().filter(|e| e.r#type == "PushEvent").filter_map(|e| e.payload.as_mut()) {
+            payload.commits = Some(vec![Commit {
+                sha: "abc123".to_string(),
+                message: "fix: handle empty spoons (#12)".to_string(),
+            }]);
+        }
+
+        let scorer = GitHubScorer::new();
+        let at = "2024-04-01T00:00:00Z".parse().unwrap();
+        let graphql = scorer.calculate_score_at(&graphql, at).unwrap();
+        let rest = scorer.calculate_score_at(&rest, at).unwrap();
+
+        // GraphQL leaves commit quality out and scales up the other signals
+        let weight = ScoringConfig::default().code_quality["commit_quality"];
+        let commit_quality = rest.detailed_components["code_quality"]["commit_quality"];
+        assert!(commit_quality > 0.0);
+        let expected = (rest.component_scores.code_quality - weight * commit_quality) / (1.0 - weight);
+        assert!((graphql.component_scores.code_quality - expected).abs() < 1e-9);
+        assert!(!graphql.detailed_components["code_quality"].contains_key("commit_quality"));
+    }
+
+    fn repository(name: &str) -> Value {
+        json!({
+            "name": name,
+            "nameWithOwner": format!("octo/{}", name),
+            "description": null,
+            "stargazerCount": 1,
+            "forkCount": 0,
+            "pushedAt": null,
+            "updatedAt": "2024-02-01T00:00:00Z",
+            "owner": { "login": "octo" },
+            "primaryLanguage": null
+        })
+    }
+
+    fn pull_requests(count: usize) -> Vec<Value> {
+        let pr = json!({ "createdAt": "2024-03-01T00:00:00Z", "mergedAt": null,
+                         "state": "OPEN", "repository": { "nameWithOwner": "octo/spoon" } });
+        vec![pr; count]
+    }
+
+    #[tokio::test]
+    async fn test_graphql_follows_cursors_up_to_the_caps() {
+        let base_url = spawn_handler(|request| {
+            let variables = &request["variables"];
+            let query = request["query"].as_str().unwrap();
+            if query.contains("contributionsCollection") {
+                assert_eq!(variables["pullRequests"], 3);
+                let mut response = canned_user();
+                let user = &mut response["data"]["user"];
+                user["repositories"]["pageInfo"] = json!({ "hasNextPage": true, "endCursor": "r1" });
+                user["pullRequests"]["pageInfo"] = json!({ "hasNextPage": true, "endCursor": "p1" });
+                response
+            } else if query.contains("repositories(") {
+                let (nodes, page_info) = match variables["after"].as_str() {
+                    Some("r1") => (vec![repository("fork")], json!({ "hasNextPage": true, "endCursor": "r2" })),
+                    Some("r2") => (vec![repository("knife")], json!({ "hasNextPage": false, "endCursor": "r3" })),
+                    other => panic!("unexpected repository cursor {:?}", other),
+                };
+                assert_eq!(variables["first"], 100);
+                json!({ "data": { "user": { "page": { "pageInfo": page_info, "nodes": nodes } } } })
+            } else {
+                assert_eq!(variables["after"], "p1");
+                // Only what is left under the cap is asked for
+                assert_eq!(variables["first"], 1);
+                let page_info = json!({ "hasNextPage": true, "endCursor": "p2" });
+                json!({ "data": { "user": { "page": { "pageInfo": page_info, "nodes": pull_requests(1) } } } })
+            }
+        })
+        .await;
+        let client = GitHubClient::new(Some("token"))
+            .unwrap()
+            .with_base_url(base_url)
+            .with_max_pull_requests(3);
+
+        let user = client.fetch_user_graphql("octo").await.unwrap().user;
+        let names: Vec<&str> = user.repositories.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["spoon", "fork", "knife"]);
+        assert_eq!(user.pull_requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_not_found_maps_to_404() {
+        let response = json!({
+            "data": { "user": null },
+            "errors": [{ "type": "NOT_FOUND", "message": "Could not resolve to a User" }]
+        });
+        let client = GitHubClient::new(Some("token"))
+            .unwrap()
+            .with_base_url(spawn_stub(response).await);
+
+        match client.fetch_user_graphql("octo").await {
+            Err(FetchError::Api { status, .. }) => assert_eq!(status, 404),
+            other => panic!("expected not found, got {:?}", other.map(|f| f.profile)),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod client;
+mod graphql;
+pub use client::*;
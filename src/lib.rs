@@ -1,2 +1,3 @@
 pub mod db;
-pub mod scoring; 
\ No newline at end of file
+pub mod github;
+pub mod scoring;
\ No newline at end of file
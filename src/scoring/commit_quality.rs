@@ -0,0 +1,169 @@
+//! Commit message heuristics, ported from `score_code_quality` and
+//! `score_commit_size` in `research/advanced_scoring.py`.
+
+use std::collections::HashMap;
+
+/// Keywords that suggest a deliberate, well-scoped change, with their bonus.
+const QUALITY_INDICATORS: [(&str, f64); 9] = [
+    ("test", 0.2),
+    ("fix", 0.1),
+    ("refactor", 0.15),
+    ("doc", 0.1),
+    ("feat", 0.15),
+    ("perf", 0.15),
+    ("style", 0.05),
+    ("chore", 0.05),
+    ("ci", 0.05),
+];
+
+/// Messages shorter than this are penalised as not descriptive.
+const SHORT_MESSAGE_LEN: usize = 10;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CommitMessageStats {
+    pub analyzed: usize,
+    /// Mean of the per-message quality scores.
+    pub message_quality: f64,
+    /// Mean of the per-message length band scores.
+    pub message_length: f64,
+    pub conventional_ratio: f64,
+    pub issue_reference_ratio: f64,
+    pub short_message_ratio: f64,
+}
+
+impl CommitMessageStats {
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut stats = Self::default();
+        let (mut conventional, mut issue_refs, mut short) = (0usize, 0usize, 0usize);
+
+        for message in messages {
+            let lower = message.to_lowercase();
+            stats.analyzed += 1;
+            stats.message_quality += message_quality(&lower);
+            stats.message_length += length_score(message.chars().count());
+            conventional += is_conventional(&lower) as usize;
+            issue_refs += has_issue_reference(&lower) as usize;
+            short += (message.chars().count() < SHORT_MESSAGE_LEN) as usize;
+        }
+
+        if stats.analyzed > 0 {
+            let n = stats.analyzed as f64;
+            stats.message_quality /= n;
+            stats.message_length /= n;
+            stats.conventional_ratio = conventional as f64 / n;
+            stats.issue_reference_ratio = issue_refs as f64 / n;
+            stats.short_message_ratio = short as f64 / n;
+        }
+        stats
+    }
+
+    /// Message content counts twice as much as length, matching the 0.20/0.10
+    /// split between the two signals in the research model. Zero when there
+    /// are no messages to judge; `CodeQuality` leaves the score out then.
+    pub fn score(&self) -> f64 {
+        if self.analyzed == 0 {
+            return 0.0;
+        }
+        (2.0 * self.message_quality + self.message_length) / 3.0
+    }
+
+    pub fn breakdown(&self) -> HashMap<String, f64> {
+        let mut breakdown = HashMap::new();
+        breakdown.insert("commit_messages_analyzed".to_string(), self.analyzed as f64);
+        breakdown.insert("commit_message_quality".to_string(), self.message_quality);
+        breakdown.insert("commit_message_length".to_string(), self.message_length);
+        breakdown.insert("commit_conventional_ratio".to_string(), self.conventional_ratio);
+        breakdown.insert("commit_issue_reference_ratio".to_string(), self.issue_reference_ratio);
+        breakdown.insert("commit_short_message_ratio".to_string(), self.short_message_ratio);
+        breakdown
+    }
+}
+
+/// Expects a lowercased message.
+fn message_quality(message: &str) -> f64 {
+    let mut quality = 0.5;
+
+    if is_conventional(message) {
+        quality += 0.2;
+    }
+    for (indicator, bonus) in QUALITY_INDICATORS {
+        if message.contains(indicator) {
+            quality += bonus;
+        }
+    }
+    if has_issue_reference(message) {
+        quality += 0.1;
+    }
+    if message.chars().count() < SHORT_MESSAGE_LEN {
+        quality -= 0.3;
+    }
+
+    quality.clamp(0.0, 1.0)
+}
+
+/// Concise but descriptive messages score best; very short ones say nothing
+/// and very long ones hint at oversized commits.
+fn length_score(len: usize) -> f64 {
+    match len {
+        0..=4 => 0.2,
+        5..=9 => 0.5,
+        10..=49 => 1.0,
+        50..=99 => 0.9,
+        100..=499 => 0.7,
+        _ => 0.4,
+    }
+}
+
+/// `type: subject` or `type(scope): subject`, as in Conventional Commits.
+fn is_conventional(message: &str) -> bool {
+    let type_len = message
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(message.len());
+    if type_len == 0 {
+        return false;
+    }
+
+    let mut rest = &message[type_len..];
+    if let Some(scoped) = rest.strip_prefix('(') {
+        match scoped.find(')') {
+            Some(end) if end > 0 => rest = &scoped[end + 1..],
+            _ => return false,
+        }
+    }
+    rest.strip_prefix(": ").is_some_and(|subject| !subject.is_empty())
+}
+
+fn has_issue_reference(message: &str) -> bool {
+    message
+        .as_bytes()
+        .windows(2)
+        .any(|pair| pair[0] == b'#' && pair[1].is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_message_signals() {
+        assert!(is_conventional("feat(api): add batch endpoint"));
+        assert!(is_conventional("fix: handle empty repos"));
+        assert!(!is_conventional("fix:missing space"));
+        assert!(!is_conventional("update stuff"));
+        assert!(has_issue_reference("closes #42"));
+        assert!(!has_issue_reference("# heading"));
+
+        let stats = CommitMessageStats::from_messages([
+            "feat(api): add batch endpoint (#42)",
+            "wip",
+        ]);
+        assert_eq!(stats.analyzed, 2);
+        assert_eq!(stats.conventional_ratio, 0.5);
+        assert_eq!(stats.issue_reference_ratio, 0.5);
+        assert_eq!(stats.short_message_ratio, 0.5);
+        // (0.5 + 0.2 conventional + 0.15 feat + 0.1 issue) and (0.5 - 0.3 short)
+        assert!((stats.message_quality - 0.575).abs() < 1e-9);
+        assert!((stats.message_length - 0.6).abs() < 1e-9);
+        assert_eq!(CommitMessageStats::default().score(), 0.0);
+    }
+}
@@ -0,0 +1,218 @@
+//! Side-by-side comparison of several users' scores.
+
+use super::DetailedScores;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A sub-metric sets a user apart when it differs from the others' mean by
+/// at least this share of the larger of the two.
+pub const DISTINCTION_THRESHOLD: f64 = 0.25;
+
+/// Strengths and weaknesses listed per user, largest difference first.
+pub const MAX_DISTINCTIONS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Comparison {
+    /// Users from highest to lowest final score.
+    pub ranking: Vec<RankedUser>,
+    /// Every component any user has a score for, by name.
+    pub components: BTreeMap<String, ComponentComparison>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RankedUser {
+    pub username: String,
+    /// 1 for the highest score; tied users share a rank.
+    pub rank: usize,
+    pub final_score: f64,
+    /// Final score minus the top final score, so 0 for the leader.
+    pub behind_leader: f64,
+    /// Sub-metrics where the user is well ahead of the others.
+    pub strengths: Vec<Distinction>,
+    /// Sub-metrics where the user is well behind the others.
+    pub weaknesses: Vec<Distinction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ComponentComparison {
+    /// Highest scorer; the first in ranking order on a tie.
+    pub leader: String,
+    pub mean: f64,
+    pub scores: BTreeMap<String, f64>,
+    /// Each user's score minus the mean.
+    pub deltas: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Distinction {
+    pub component: String,
+    pub metric: String,
+    pub value: f64,
+    /// Mean of the metric over the other users.
+    pub others_mean: f64,
+    /// `value - others_mean` as a share of the larger of the two, from -1 to 1
+    /// for non-negative metrics.
+    pub relative_difference: f64,
+}
+
+/// Ranks users by final score, compares each component across them and
+/// picks out the sub-metrics that set each user apart. Users missing a
+/// component or sub-metric count as 0 for it.
+pub fn compare(scores: &[(&str, &DetailedScores)]) -> Comparison {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].1.final_score.total_cmp(&scores[a].1.final_score));
+    let top = order.first().map_or(0.0, |&i| scores[i].1.final_score);
+
+    let component_maps: Vec<BTreeMap<String, f64>> =
+        scores.iter().map(|(_, s)| s.component_scores.to_map()).collect();
+    let component_names: Vec<&String> = names(component_maps.iter().flat_map(|m| m.keys()));
+
+    let components = component_names
+        .into_iter()
+        .map(|name| {
+            let values: Vec<f64> = component_maps.iter().map(|m| m.get(name).copied().unwrap_or(0.0)).collect();
+            let mean = mean(&values);
+            let leader = order
+                .iter()
+                .copied()
+                .reduce(|best, i| if values[i] > values[best] { i } else { best })
+                .map(|i| scores[i].0.to_string())
+                .unwrap_or_default();
+            let comparison = ComponentComparison {
+                leader,
+                mean,
+                scores: scores.iter().zip(&values).map(|((user, _), v)| (user.to_string(), *v)).collect(),
+                deltas: scores.iter().zip(&values).map(|((user, _), v)| (user.to_string(), v - mean)).collect(),
+            };
+            (name.clone(), comparison)
+        })
+        .collect();
+
+    let mut ranking: Vec<RankedUser> = Vec::with_capacity(scores.len());
+    for (position, &i) in order.iter().enumerate() {
+        let (username, score) = scores[i];
+        let rank = match ranking.last() {
+            Some(previous) if previous.final_score == score.final_score => previous.rank,
+            _ => position + 1,
+        };
+        let (strengths, weaknesses) = distinctions(scores, i);
+        ranking.push(RankedUser {
+            username: username.to_string(),
+            rank,
+            final_score: score.final_score,
+            behind_leader: score.final_score - top,
+            strengths,
+            weaknesses,
+        });
+    }
+
+    Comparison { ranking, components }
+}
+
+/// The sub-metrics user `i` is furthest ahead of and behind the others on.
+fn distinctions(scores: &[(&str, &DetailedScores)], i: usize) -> (Vec<Distinction>, Vec<Distinction>) {
+    let metric = |s: &DetailedScores, component: &str, metric: &str| {
+        s.detailed_components.get(component).and_then(|m| m.get(metric)).copied().unwrap_or(0.0)
+    };
+    let detailed = &scores[i].1.detailed_components;
+    let mut all: Vec<Distinction> = Vec::new();
+    for component in names(detailed.keys()) {
+        for name in names(detailed[component].keys()) {
+            let value = metric(scores[i].1, component, name);
+            let others: Vec<f64> = scores
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, (_, s))| metric(s, component, name))
+                .collect();
+            if others.is_empty() {
+                continue;
+            }
+            let others_mean = mean(&others);
+            let scale = value.abs().max(others_mean.abs());
+            if scale == 0.0 {
+                continue;
+            }
+            all.push(Distinction {
+                component: component.clone(),
+                metric: name.clone(),
+                value,
+                others_mean,
+                relative_difference: (value - others_mean) / scale,
+            });
+        }
+    }
+    all.retain(|d| d.relative_difference.abs() >= DISTINCTION_THRESHOLD);
+    all.sort_by(|a, b| b.relative_difference.abs().total_cmp(&a.relative_difference.abs()));
+
+    let (strengths, weaknesses): (Vec<_>, Vec<_>) = all.into_iter().partition(|d| d.relative_difference > 0.0);
+    (
+        strengths.into_iter().take(MAX_DISTINCTIONS).collect(),
+        weaknesses.into_iter().take(MAX_DISTINCTIONS).collect(),
+    )
+}
+
+/// Sorted, deduplicated names, so output doesn't depend on hash order.
+fn names<'a>(keys: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+    let mut names: Vec<&String> = keys.collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::ScoreComponents;
+    use std::collections::HashMap;
+
+    fn scores(final_score: f64, code_quality: f64, stars: f64) -> DetailedScores {
+        DetailedScores {
+            final_score,
+            component_scores: ScoreComponents {
+                code_quality,
+                ..ScoreComponents::default()
+            },
+            detailed_components: HashMap::from([
+                ("code_quality".to_string(), HashMap::from([("documentation".to_string(), code_quality)])),
+                ("repo_significance".to_string(), HashMap::from([("stars".to_string(), stars)])),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_compare_ranks_and_distinguishes_users() {
+        let (alice, bob, carol) = (scores(0.8, 0.9, 0.1), scores(0.5, 0.3, 0.1), scores(0.8, 0.6, 0.5));
+        let comparison = compare(&[("bob", &bob), ("alice", &alice), ("carol", &carol)]);
+
+        let ranking: Vec<(&str, usize)> =
+            comparison.ranking.iter().map(|u| (u.username.as_str(), u.rank)).collect();
+        assert_eq!(ranking, vec![("alice", 1), ("carol", 1), ("bob", 3)]);
+        assert!((comparison.ranking[2].behind_leader + 0.3).abs() < 1e-9);
+
+        let code_quality = &comparison.components["code_quality"];
+        assert_eq!(code_quality.leader, "alice");
+        assert!((code_quality.mean - 0.6).abs() < 1e-9);
+        assert!((code_quality.deltas["bob"] + 0.3).abs() < 1e-9);
+        // Every built-in component is compared; ties go to the higher ranked user
+        assert_eq!(comparison.components["community_engagement"].leader, "alice");
+
+        let carol = &comparison.ranking[1];
+        assert_eq!(carol.strengths.len(), 1);
+        assert_eq!(carol.strengths[0].metric, "stars");
+        assert!((carol.strengths[0].relative_difference - 0.8).abs() < 1e-9);
+        assert!(carol.weaknesses.is_empty());
+
+        let bob = &comparison.ranking[2];
+        assert!(bob.strengths.is_empty());
+        let weaknesses: Vec<&str> = bob.weaknesses.iter().map(|d| d.metric.as_str()).collect();
+        assert_eq!(weaknesses, vec!["stars", "documentation"]);
+    }
+}
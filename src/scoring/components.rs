@@ -0,0 +1,335 @@
+//! The sub-scores that make up a final score. The four built-in components
+//! implement [`ScoringComponent`] like any user-supplied one.
+
+use super::{CommitMessageStats, GitHubUser, PullRequest, ScoringConfig};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// What a component gets to look at besides the user.
+pub struct ScoringContext<'a> {
+    pub config: &'a ScoringConfig,
+    /// Reference time for ages and decay; activity after it has already been
+    /// left out of the user.
+    pub now: DateTime<Utc>,
+}
+
+impl<'a> ScoringContext<'a> {
+    pub fn new(config: &'a ScoringConfig, now: DateTime<Utc>) -> Self {
+        Self { config, now }
+    }
+
+    /// Decay weight of something that happened at `at`.
+    pub fn decay(&self, at: DateTime<Utc>) -> f64 {
+        self.config.time_decay.weight(at, self.now)
+    }
+}
+
+/// A component's score in `[0, 1]` plus the signals behind it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComponentResult {
+    pub score: f64,
+    pub breakdown: HashMap<String, f64>,
+}
+
+impl ComponentResult {
+    pub fn new(score: f64, breakdown: HashMap<String, f64>) -> Self {
+        Self { score, breakdown }
+    }
+
+    /// Scores a breakdown as the weighted sum of its signals. Signals without
+    /// a weight are kept in the breakdown for information only.
+    pub fn weighted(weights: &HashMap<String, f64>, breakdown: HashMap<String, f64>) -> Self {
+        // Summed in key order so the result doesn't depend on hash order
+        let mut keys: Vec<&String> = weights.keys().collect();
+        keys.sort();
+        let score = keys
+            .into_iter()
+            .map(|key| weights[key] * breakdown.get(key).copied().unwrap_or(0.0))
+            .sum();
+        Self { score, breakdown }
+    }
+
+    /// Like [`Self::weighted`], but leaves out the signal `missing`, which
+    /// couldn't be measured, and scales the other weights up to make up its
+    /// share.
+    pub fn weighted_without(weights: &HashMap<String, f64>, breakdown: HashMap<String, f64>, missing: &str) -> Self {
+        let total: f64 = weights.values().sum();
+        let kept: f64 = weights.iter().filter(|(key, _)| *key != missing).map(|(_, w)| w).sum();
+        if kept <= 0.0 {
+            return Self::weighted(weights, breakdown);
+        }
+        let scaled: HashMap<String, f64> = weights
+            .iter()
+            .filter(|(key, _)| *key != missing)
+            .map(|(key, w)| (key.clone(), w * total / kept))
+            .collect();
+        Self::weighted(&scaled, breakdown)
+    }
+}
+
+/// One weighted part of the final score.
+///
+/// Register extra components with [`super::GitHubScorer::with_component`].
+/// The final score is the weighted mean of all component scores, so a custom
+/// component's weight is relative to the built-in ones.
+pub trait ScoringComponent: Send + Sync {
+    /// Key for the component in `component_scores` and `detailed_components`.
+    fn name(&self) -> &str;
+
+    fn weight(&self) -> f64;
+
+    fn score(&self, user: &GitHubUser, ctx: &ScoringContext) -> ComponentResult;
+}
+
+/// Commit volume and recency, issue resolution and PR acceptance.
+pub struct ContributionWeight {
+    pub weight: f64,
+}
+
+/// Stars, forks and how recently the user's repositories were updated.
+pub struct RepoSignificance {
+    pub weight: f64,
+}
+
+/// Review participation, documented repositories and commit messages.
+pub struct CodeQuality {
+    pub weight: f64,
+}
+
+/// Discussions, breadth of projects and repositories maintained.
+pub struct CommunityEngagement {
+    pub weight: f64,
+}
+
+/// The built-in components, weighted as in `config.weights`.
+pub fn builtin_components(config: &ScoringConfig) -> Vec<Box<dyn ScoringComponent>> {
+    let weight = |key: &str| config.weights.get(key).copied().unwrap_or(0.0);
+    vec![
+        Box::new(ContributionWeight { weight: weight("contribution_weight") }),
+        Box::new(RepoSignificance { weight: weight("repo_significance") }),
+        Box::new(CodeQuality { weight: weight("code_quality") }),
+        Box::new(CommunityEngagement { weight: weight("community_engagement") }),
+    ]
+}
+
+impl ScoringComponent for ContributionWeight {
+    fn name(&self) -> &str {
+        "contribution_weight"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, user: &GitHubUser, ctx: &ScoringContext) -> ComponentResult {
+        let thresholds = &ctx.config.thresholds;
+        let decay = |at: DateTime<Utc>| ctx.decay(at);
+
+        let pushes: Vec<(f64, usize)> = user.events
+            .iter()
+            .map(|e| (decay(e.created_at), e.commit_count(ctx.config.distinct_commits_only)))
+            .filter(|(_, commits)| *commits > 0)
+            .collect();
+
+        let total_commits: usize = pushes.iter().map(|(_, commits)| commits).sum();
+        let decayed_commits: f64 = pushes.iter().map(|(w, commits)| w * *commits as f64).sum();
+
+        let commit_frequency = (decayed_commits / thresholds.commits).min(1.0);
+
+        // Mean decay weight per commit, so fresh commits score close to 1
+        let recency_score = if total_commits > 0 {
+            decayed_commits / total_commits as f64
+        } else {
+            0.0
+        };
+
+        let issue_weights: Vec<(f64, bool)> = user.events
+            .iter()
+            .filter(|e| e.r#type == "IssuesEvent")
+            .map(|e| (
+                decay(e.created_at),
+                e.payload.as_ref().is_some_and(|p| p.action.as_deref() == Some("closed")),
+            ))
+            .collect();
+
+        let total_issues: f64 = issue_weights.iter().map(|(w, _)| w).sum();
+        let closed_issues: f64 = issue_weights.iter().filter(|(_, closed)| *closed).map(|(w, _)| w).sum();
+
+        let issue_resolution = if total_issues > 0.0 {
+            closed_issues / total_issues
+        } else {
+            0.0
+        };
+
+        // PRs without dates (older cache entries) count fully
+        let pr_weight = |pr: &PullRequest| pr.created_at.or(pr.merged_at).map_or(1.0, decay);
+
+        let merged_prs: f64 = user.pull_requests
+            .iter()
+            .filter(|pr| pr.is_merged())
+            .map(pr_weight)
+            .sum();
+
+        // Open PRs haven't been accepted or rejected yet
+        let decided_prs: f64 = user.pull_requests
+            .iter()
+            .filter(|pr| !pr.is_open())
+            .map(pr_weight)
+            .sum();
+
+        let pr_acceptance = if decided_prs > 0.0 {
+            merged_prs / decided_prs
+        } else {
+            0.0
+        };
+
+        let external_merged_prs: f64 = user.pull_requests
+            .iter()
+            .filter(|pr| pr.is_merged() && pr.is_external(&user.login))
+            .map(pr_weight)
+            .sum();
+
+        let external_contributions = (external_merged_prs / thresholds.external_pull_requests).min(1.0);
+
+        let mut component_scores = HashMap::new();
+        component_scores.insert("commit_frequency".to_string(), commit_frequency);
+        component_scores.insert("commit_recency".to_string(), recency_score);
+        component_scores.insert("issue_resolution".to_string(), issue_resolution.min(1.0));
+        component_scores.insert("pr_acceptance".to_string(), pr_acceptance.min(1.0));
+        component_scores.insert("external_contributions".to_string(), external_contributions);
+
+        ComponentResult::weighted(&ctx.config.contribution, component_scores)
+    }
+}
+
+impl ScoringComponent for RepoSignificance {
+    fn name(&self) -> &str {
+        "repo_significance"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, user: &GitHubUser, ctx: &ScoringContext) -> ComponentResult {
+        let thresholds = &ctx.config.thresholds;
+        if user.repositories.is_empty() {
+            return ComponentResult::default();
+        }
+
+        let total_stars: u32 = user.repositories.iter().map(|r| r.stargazers_count).sum();
+        let total_forks: u32 = user.repositories.iter().map(|r| r.forks_count).sum();
+
+        let star_score = (total_stars as f64).ln_1p() / thresholds.stars.ln_1p();
+        let fork_score = (total_forks as f64).ln_1p() / thresholds.forks.ln_1p();
+
+        let repo_activity: f64 = user.repositories
+            .iter()
+            .map(|r| ctx.decay(r.updated_at))
+            .sum();
+
+        let activity_score = repo_activity / user.repositories.len() as f64;
+        let ecosystem_score = (star_score + fork_score) / 2.0;
+
+        let mut component_scores = HashMap::new();
+        component_scores.insert("stars".to_string(), star_score.min(1.0));
+        component_scores.insert("forks".to_string(), fork_score.min(1.0));
+        component_scores.insert("repo_activity".to_string(), activity_score.min(1.0));
+        component_scores.insert("ecosystem_impact".to_string(), ecosystem_score.min(1.0));
+
+        ComponentResult::weighted(&ctx.config.repo_significance, component_scores)
+    }
+}
+
+impl ScoringComponent for CodeQuality {
+    fn name(&self) -> &str {
+        "code_quality"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, user: &GitHubUser, ctx: &ScoringContext) -> ComponentResult {
+        let thresholds = &ctx.config.thresholds;
+        let review_events = user.events
+            .iter()
+            .filter(|e| e.r#type == "PullRequestReviewEvent")
+            .count();
+
+        let review_score = (review_events as f64 / thresholds.reviews).min(1.0);
+
+        let doc_repos = user.repositories
+            .iter()
+            .filter(|r| r.description.as_ref().is_some_and(|d| d.len() as f64 > thresholds.description_length))
+            .count();
+
+        let doc_score = if !user.repositories.is_empty() {
+            doc_repos as f64 / user.repositories.len() as f64
+        } else {
+            0.0
+        };
+
+        let messages = user.events
+            .iter()
+            .filter(|e| e.r#type == "PushEvent")
+            .filter_map(|e| e.payload.as_ref()?.commits.as_ref())
+            .flatten()
+            .map(|c| c.message.as_str());
+        let commit_stats = CommitMessageStats::from_messages(messages);
+
+        let mut component_scores = commit_stats.breakdown();
+        component_scores.insert("code_review_participation".to_string(), review_score);
+        component_scores.insert("documentation".to_string(), doc_score.min(1.0));
+
+        // No messages, as from the GraphQL source, says nothing about their
+        // quality, so the signal is left out rather than scored 0
+        if commit_stats.analyzed == 0 {
+            return ComponentResult::weighted_without(&ctx.config.code_quality, component_scores, "commit_quality");
+        }
+        component_scores.insert("commit_quality".to_string(), commit_stats.score());
+        ComponentResult::weighted(&ctx.config.code_quality, component_scores)
+    }
+}
+
+impl ScoringComponent for CommunityEngagement {
+    fn name(&self) -> &str {
+        "community_engagement"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, user: &GitHubUser, ctx: &ScoringContext) -> ComponentResult {
+        let thresholds = &ctx.config.thresholds;
+        let discussion_events = user.events
+            .iter()
+            .filter(|e| e.r#type == "IssueCommentEvent" || e.r#type == "CommitCommentEvent")
+            .count();
+
+        let discussion_score = (discussion_events as f64 / thresholds.discussions).min(1.0);
+
+        let contributed_repos: std::collections::HashSet<_> = user.events
+            .iter()
+            .filter_map(|e| e.repo.as_ref().map(|r| r.name.clone()))
+            .collect();
+
+        let diversity_score = (contributed_repos.len() as f64 / thresholds.contributed_repos).min(1.0);
+
+        let owned_repos = user.repositories
+            .iter()
+            .filter(|r| r.owner.login == user.login)
+            .count();
+
+        let maintainer_score = (owned_repos as f64 / thresholds.owned_repos).min(1.0);
+
+        let mut component_scores = HashMap::new();
+        component_scores.insert("discussions".to_string(), discussion_score);
+        component_scores.insert("project_diversity".to_string(), diversity_score);
+        component_scores.insert("maintainer_roles".to_string(), maintainer_score);
+
+        ComponentResult::weighted(&ctx.config.community, component_scores)
+    }
+}
+
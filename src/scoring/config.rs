@@ -0,0 +1,340 @@
+use super::{ScoringError, TimeDecay};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Tolerance when checking that a weight group sums to 1.
+const WEIGHT_SUM_TOLERANCE: f64 = 1e-6;
+
+const GROUP_KEYS: [&str; 4] = [
+    "contribution_weight",
+    "repo_significance",
+    "code_quality",
+    "community_engagement",
+];
+const CONTRIBUTION_KEYS: [&str; 5] = [
+    "commit_frequency",
+    "commit_recency",
+    "issue_resolution",
+    "pr_acceptance",
+    "external_contributions",
+];
+const REPO_SIGNIFICANCE_KEYS: [&str; 4] = ["stars", "forks", "repo_activity", "ecosystem_impact"];
+const CODE_QUALITY_KEYS: [&str; 3] = ["code_review_participation", "documentation", "commit_quality"];
+const COMMUNITY_KEYS: [&str; 3] = ["discussions", "project_diversity", "maintainer_roles"];
+/// Sections that are weight tables, which a config file replaces as a whole.
+const WEIGHT_TABLES: [&str; 5] = ["weights", "contribution", "repo_significance", "code_quality", "community"];
+
+/// Weights and normalization constants for [`super::GitHubScorer`].
+///
+/// Sections left out of a config file keep their defaults; a weight table that
+/// is present replaces the default table entirely.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    /// Weight of each sub-score in the final score.
+    pub weights: HashMap<String, f64>,
+    pub contribution: HashMap<String, f64>,
+    pub repo_significance: HashMap<String, f64>,
+    pub code_quality: HashMap<String, f64>,
+    pub community: HashMap<String, f64>,
+    pub thresholds: Thresholds,
+    pub time_decay: TimeDecay,
+    pub distinct_commits_only: bool,
+}
+
+/// Amounts at which a signal saturates to a full score.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    pub commits: f64,
+    /// Stars and forks are scored on a log scale up to these totals.
+    pub stars: f64,
+    pub forks: f64,
+    pub external_pull_requests: f64,
+    pub reviews: f64,
+    pub discussions: f64,
+    pub contributed_repos: f64,
+    pub owned_repos: f64,
+    /// Descriptions longer than this many bytes count as documentation.
+    pub description_length: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            commits: 100.0,
+            stars: 1000.0,
+            forks: 500.0,
+            external_pull_requests: 20.0,
+            reviews: 50.0,
+            discussions: 50.0,
+            contributed_repos: 10.0,
+            owned_repos: 5.0,
+            description_length: 50.0,
+        }
+    }
+}
+
+fn weights(entries: &[(&str, f64)]) -> HashMap<String, f64> {
+    entries.iter().map(|(key, weight)| (key.to_string(), *weight)).collect()
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            weights: weights(&[
+                ("contribution_weight", 0.35),
+                ("repo_significance", 0.30),
+                ("code_quality", 0.20),
+                ("community_engagement", 0.15),
+            ]),
+            contribution: weights(&[
+                ("commit_frequency", 0.30),
+                ("commit_recency", 0.20),
+                ("issue_resolution", 0.15),
+                ("pr_acceptance", 0.20),
+                ("external_contributions", 0.15),
+            ]),
+            repo_significance: weights(&[
+                ("stars", 0.30),
+                ("forks", 0.25),
+                ("repo_activity", 0.25),
+                ("ecosystem_impact", 0.20),
+            ]),
+            code_quality: weights(&[
+                ("code_review_participation", 0.30),
+                ("documentation", 0.30),
+                ("commit_quality", 0.40),
+            ]),
+            community: weights(&[
+                ("discussions", 0.30),
+                ("project_diversity", 0.40),
+                ("maintainer_roles", 0.30),
+            ]),
+            thresholds: Thresholds::default(),
+            time_decay: TimeDecay::default(),
+            distinct_commits_only: false,
+        }
+    }
+}
+
+impl ScoringConfig {
+    /// Reads and validates a config file; `.json` files are parsed as JSON and
+    /// anything else as TOML.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ScoringError> {
+        Self::from_file_over(path, &Self::default())
+    }
+
+    /// Like [`Self::from_file`], but values the file leaves out are taken
+    /// from `base` rather than the defaults. A weight table in the file still
+    /// replaces `base`'s table entirely; other sections are merged key by key.
+    pub fn from_file_over(path: impl AsRef<Path>, base: &Self) -> Result<Self, ScoringError> {
+        let path = path.as_ref();
+        let parse_error = |message: String| ScoringError::ConfigParse {
+            path: path.display().to_string(),
+            message,
+        };
+        let contents = std::fs::read_to_string(path).map_err(|e| ScoringError::ConfigRead {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let overrides: Value = if is_json {
+            serde_json::from_str(&contents).map_err(|e| parse_error(e.to_string()))?
+        } else {
+            toml::from_str(&contents).map_err(|e| parse_error(e.message().to_string()))?
+        };
+
+        let config = base.merged_with(overrides).map_err(parse_error)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn merged_with(&self, overrides: Value) -> Result<Self, String> {
+        let Value::Object(overrides) = overrides else {
+            return Err("expected a table of settings".to_string());
+        };
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        let sections = merged.as_object_mut().ok_or("config is not a table")?;
+        for (key, value) in overrides {
+            match (sections.get_mut(&key), value) {
+                (Some(Value::Object(section)), Value::Object(values)) if !WEIGHT_TABLES.contains(&key.as_str()) => {
+                    section.extend(values);
+                }
+                (_, value) => {
+                    sections.insert(key, value);
+                }
+            }
+        }
+        serde_json::from_value(merged).map_err(|e| e.to_string())
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, ScoringError> {
+        let config: Self = toml::from_str(contents).map_err(|e| ScoringError::ConfigParse {
+            path: "<toml>".to_string(),
+            message: e.message().to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json_str(contents: &str) -> Result<Self, ScoringError> {
+        let config: Self = serde_json::from_str(contents).map_err(|e| ScoringError::ConfigParse {
+            path: "<json>".to_string(),
+            message: e.to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Fingerprint of the config; changes whenever any value does.
+    pub fn version(&self) -> String {
+        fingerprint(&serde_json::to_value(self).unwrap_or(Value::Null))
+    }
+
+    /// Checks that every weight group only names known sub-scores, has no
+    /// negative weights and sums to 1, and that every constant is positive.
+    pub fn validate(&self) -> Result<(), ScoringError> {
+        validate_group("weights", &self.weights, &GROUP_KEYS)?;
+        validate_group("contribution", &self.contribution, &CONTRIBUTION_KEYS)?;
+        validate_group("repo_significance", &self.repo_significance, &REPO_SIGNIFICANCE_KEYS)?;
+        validate_group("code_quality", &self.code_quality, &CODE_QUALITY_KEYS)?;
+        validate_group("community", &self.community, &COMMUNITY_KEYS)?;
+
+        let t = &self.thresholds;
+        for (name, value) in [
+            ("thresholds.commits", t.commits),
+            ("thresholds.stars", t.stars),
+            ("thresholds.forks", t.forks),
+            ("thresholds.external_pull_requests", t.external_pull_requests),
+            ("thresholds.reviews", t.reviews),
+            ("thresholds.discussions", t.discussions),
+            ("thresholds.contributed_repos", t.contributed_repos),
+            ("thresholds.owned_repos", t.owned_repos),
+            ("thresholds.description_length", t.description_length),
+            ("time_decay.half_life_days", self.time_decay.half_life_days),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(ScoringError::InvalidConstant { name: name.to_string(), value });
+            }
+        }
+
+        let floor = self.time_decay.floor;
+        if !(0.0..=1.0).contains(&floor) {
+            return Err(ScoringError::InvalidConstant {
+                name: "time_decay.floor".to_string(),
+                value: floor,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Short, stable fingerprint of a JSON value: FNV-1a over the value with
+/// object keys sorted.
+pub(crate) fn fingerprint(value: &Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical);
+
+    let hash = canonical.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for key in keys {
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+                out.push(',');
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for item in items {
+                write_canonical(item, out);
+                out.push(',');
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+fn validate_group(group: &str, weights: &HashMap<String, f64>, known: &[&str]) -> Result<(), ScoringError> {
+    for (key, &weight) in weights {
+        if !known.contains(&key.as_str()) {
+            return Err(ScoringError::UnknownWeight {
+                group: group.to_string(),
+                key: key.clone(),
+            });
+        }
+        if !(weight.is_finite() && weight >= 0.0) {
+            return Err(ScoringError::InvalidConstant {
+                name: format!("{}.{}", group, key),
+                value: weight,
+            });
+        }
+    }
+
+    let sum: f64 = weights.values().sum();
+    if (sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+        return Err(ScoringError::WeightSum { group: group.to_string(), sum });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        ScoringConfig::default().validate().unwrap();
+
+        let example = include_str!("../../scoring.example.toml");
+        assert_eq!(ScoringConfig::from_toml_str(example).unwrap(), ScoringConfig::default());
+    }
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config = ScoringConfig::from_json_str(
+            r#"{ "thresholds": { "commits": 250 }, "distinct_commits_only": true }"#,
+        )
+        .unwrap();
+        assert_eq!(config.thresholds.commits, 250.0);
+        assert_eq!(config.thresholds.stars, 1000.0);
+        assert!(config.distinct_commits_only);
+        assert_eq!(config.weights, ScoringConfig::default().weights);
+    }
+
+    #[test]
+    fn test_malformed_configs_are_rejected() {
+        let err = ScoringConfig::from_toml_str("[weights]\ncontribution_weight = 0.5\ncode_quality = 0.4\n")
+            .unwrap_err();
+        assert!(matches!(err, ScoringError::WeightSum { ref group, .. } if group == "weights"));
+
+        let err = ScoringConfig::from_toml_str("[community]\ndiscussions = 0.5\nstars = 0.5\n").unwrap_err();
+        assert!(matches!(err, ScoringError::UnknownWeight { ref key, .. } if key == "stars"));
+
+        let err = ScoringConfig::from_toml_str("[thresholds]\nforks = 0\n").unwrap_err();
+        assert!(matches!(err, ScoringError::InvalidConstant { ref name, .. } if name == "thresholds.forks"));
+
+        let err = ScoringConfig::from_toml_str("[thresholds]\nstarz = 10\n").unwrap_err();
+        assert!(matches!(err, ScoringError::ConfigParse { .. }));
+
+        let err = ScoringConfig::from_file("does/not/exist.toml").unwrap_err();
+        assert!(matches!(err, ScoringError::ConfigRead { .. }));
+    }
+}
@@ -0,0 +1,108 @@
+//! Time decay for activity, generalising `_apply_time_decay` in
+//! `research/advanced_scoring.py`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecayFunction {
+    /// Weight halves every `half_life_days`.
+    #[default]
+    Exponential,
+    /// Weight drops by half after `half_life_days` and reaches zero at twice that.
+    Linear,
+}
+
+impl FromStr for DecayFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "exponential" => Ok(Self::Exponential),
+            "linear" => Ok(Self::Linear),
+            other => Err(format!("unknown decay function '{}', expected 'exponential' or 'linear'", other)),
+        }
+    }
+}
+
+/// How much an activity of a given age still counts towards a score.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeDecay {
+    pub function: DecayFunction,
+    pub half_life_days: f64,
+    /// Lowest weight any activity is given, however old.
+    pub floor: f64,
+}
+
+impl Default for TimeDecay {
+    fn default() -> Self {
+        Self {
+            function: DecayFunction::Exponential,
+            half_life_days: 180.0,
+            floor: 0.1,
+        }
+    }
+}
+
+impl TimeDecay {
+    /// Weight in `[floor, 1]` for something that happened `age_days` ago.
+    /// Activity dated in the future counts fully.
+    pub fn weight_for_age(&self, age_days: f64) -> f64 {
+        let age = age_days.max(0.0);
+        let weight = if self.half_life_days <= 0.0 {
+            if age == 0.0 { 1.0 } else { 0.0 }
+        } else {
+            match self.function {
+                DecayFunction::Exponential => 0.5_f64.powf(age / self.half_life_days),
+                DecayFunction::Linear => 1.0 - age / (2.0 * self.half_life_days),
+            }
+        };
+        weight.clamp(self.floor.clamp(0.0, 1.0), 1.0)
+    }
+
+    pub fn weight(&self, at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+        self.weight_for_age((now - at).num_seconds() as f64 / 86_400.0)
+    }
+
+    pub fn breakdown(&self) -> HashMap<String, f64> {
+        let mut breakdown = HashMap::new();
+        breakdown.insert("half_life_days".to_string(), self.half_life_days);
+        breakdown.insert("floor".to_string(), self.floor);
+        breakdown.insert(
+            "exponential".to_string(),
+            (self.function == DecayFunction::Exponential) as u8 as f64,
+        );
+        breakdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_functions() {
+        let exponential = TimeDecay::default();
+        assert_eq!(exponential.weight_for_age(0.0), 1.0);
+        assert!((exponential.weight_for_age(180.0) - 0.5).abs() < 1e-9);
+        assert!((exponential.weight_for_age(360.0) - 0.25).abs() < 1e-9);
+        assert_eq!(exponential.weight_for_age(10_000.0), 0.1);
+        assert_eq!(exponential.weight_for_age(-5.0), 1.0);
+
+        let linear = TimeDecay {
+            function: DecayFunction::Linear,
+            half_life_days: 100.0,
+            floor: 0.0,
+        };
+        assert!((linear.weight_for_age(50.0) - 0.75).abs() < 1e-9);
+        assert!((linear.weight_for_age(100.0) - 0.5).abs() < 1e-9);
+        assert_eq!(linear.weight_for_age(250.0), 0.0);
+
+        assert_eq!("Linear".parse::<DecayFunction>(), Ok(DecayFunction::Linear));
+        assert!("step".parse::<DecayFunction>().is_err());
+    }
+}
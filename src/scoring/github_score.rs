@@ -1,7 +1,15 @@
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use thiserror::Error;
+use super::config::fingerprint;
+use super::{builtin_components, Clock, ScoringComponent, ScoringConfig, ScoringContext, SystemClock, TimeDecay};
+
+/// Bump whenever a code change alters the scores produced for the same
+/// config, so scores cached under the old algorithm are recomputed.
+pub const ALGORITHM_VERSION: u32 = 2;
 
 #[derive(Debug, Error)]
 pub enum ScoringError {
@@ -9,6 +17,20 @@ pub enum ScoringError {
     ApiError(String),
     #[error("Invalid data: {0}")]
     InvalidData(String),
+    #[error("Failed to read scoring config {path}: {message}")]
+    ConfigRead { path: String, message: String },
+    #[error("Failed to parse scoring config {path}: {message}")]
+    ConfigParse { path: String, message: String },
+    #[error("Weights in [{group}] must sum to 1, got {sum}")]
+    WeightSum { group: String, sum: f64 },
+    #[error("Unknown weight '{key}' in [{group}]")]
+    UnknownWeight { group: String, key: String },
+    #[error("{name} must be positive, got {value}")]
+    InvalidConstant { name: String, value: f64 },
+    #[error("Invalid {name} '{value}': {message}")]
+    InvalidOverride { name: String, value: String, message: String },
+    #[error("Unknown scoring profile '{0}'")]
+    UnknownProfile(String),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,6 +39,46 @@ pub struct GitHubUser {
     pub repositories: Vec<Repository>,
     pub events: Vec<Event>,
     pub pull_requests: Vec<PullRequest>,
+    /// Contribution totals, only available from the GraphQL data source.
+    #[serde(default)]
+    pub contributions: Option<ContributionTotals>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContributionTotals {
+    pub commits: u32,
+    pub issues: u32,
+    pub pull_requests: u32,
+    pub reviews: u32,
+}
+
+impl GitHubUser {
+    /// The user as of `at`: events and pull requests created after it are
+    /// dropped. Repositories are kept, as only their latest state is known.
+    pub fn as_of(&self, at: DateTime<Utc>) -> Cow<'_, GitHubUser> {
+        let pr_time = |pr: &PullRequest| pr.created_at.or(pr.merged_at);
+        let is_later = self.events.iter().any(|e| e.created_at > at)
+            || self.pull_requests.iter().any(|pr| pr_time(pr).is_some_and(|t| t > at));
+        if !is_later {
+            return Cow::Borrowed(self);
+        }
+
+        Cow::Owned(GitHubUser {
+            events: self.events.iter().filter(|e| e.created_at <= at).cloned().collect(),
+            pull_requests: self.pull_requests
+                .iter()
+                .filter(|pr| pr_time(pr).is_none_or(|t| t <= at))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        })
+    }
+}
+
+impl ContributionTotals {
+    pub fn total(&self) -> u32 {
+        self.commits + self.issues + self.pull_requests + self.reviews
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +90,7 @@ pub struct Repository {
     pub updated_at: DateTime<Utc>,
     pub owner: User,
     pub description: Option<String>,
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,22 +111,118 @@ pub struct Repo {
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct EventPayload {
     pub action: Option<String>,
+    /// PushEvent: number of commits in the push.
+    #[serde(default)]
+    pub size: Option<u32>,
+    /// PushEvent: commits in the push not already on another branch.
+    #[serde(default)]
+    pub distinct_size: Option<u32>,
+    /// PushEvent: up to 20 of the pushed commits.
+    #[serde(default)]
+    pub commits: Option<Vec<Commit>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Commit {
+    pub sha: String,
+    pub message: String,
+}
+
+impl Event {
+    /// Number of commits the event carries: 0 for anything but a PushEvent,
+    /// and at least 1 for a push whose payload lacks counts.
+    pub fn commit_count(&self, distinct_only: bool) -> usize {
+        if self.r#type != "PushEvent" {
+            return 0;
+        }
+        let payload = self.payload.as_ref();
+        let size = if distinct_only {
+            payload.and_then(|p| p.distinct_size.or(p.size))
+        } else {
+            payload.and_then(|p| p.size)
+        };
+        size.map(|n| n as usize)
+            .or_else(|| payload.and_then(|p| p.commits.as_ref()).map(Vec::len))
+            .unwrap_or(1)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PullRequest {
     pub merged_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub state: PullRequestState,
+    /// Target repository as `owner/name`.
+    #[serde(default)]
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestState {
+    #[default]
+    Open,
+    Closed,
+    Merged,
+}
+
+impl PullRequest {
+    /// REST listings report merged PRs as `closed` with a `merged_at` time.
+    pub fn is_merged(&self) -> bool {
+        self.state == PullRequestState::Merged || self.merged_at.is_some()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == PullRequestState::Open && self.merged_at.is_none()
+    }
+
+    /// Whether the PR targets a repository owned by someone other than `login`.
+    pub fn is_external(&self, login: &str) -> bool {
+        self.repository
+            .as_deref()
+            .and_then(|full_name| full_name.split('/').next())
+            .is_some_and(|owner| !owner.eq_ignore_ascii_case(login))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ScoreComponents {
     pub contribution_weight: f64,
     pub repo_significance: f64,
     pub code_quality: f64,
     pub community_engagement: f64,
+    /// Scores of registered custom components, by name.
+    #[serde(flatten)]
+    pub custom: HashMap<String, f64>,
+}
+
+impl ScoreComponents {
+    /// Every component score by name, built-in and custom.
+    pub fn to_map(&self) -> BTreeMap<String, f64> {
+        let mut scores: BTreeMap<String, f64> = self.custom.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        scores.insert("contribution_weight".to_string(), self.contribution_weight);
+        scores.insert("repo_significance".to_string(), self.repo_significance);
+        scores.insert("code_quality".to_string(), self.code_quality);
+        scores.insert("community_engagement".to_string(), self.community_engagement);
+        scores
+    }
+
+    fn set(&mut self, name: &str, score: f64) {
+        match name {
+            "contribution_weight" => self.contribution_weight = score,
+            "repo_significance" => self.repo_significance = score,
+            "code_quality" => self.code_quality = score,
+            "community_engagement" => self.community_engagement = score,
+            _ => {
+                self.custom.insert(name.to_string(), score);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -74,50 +233,19 @@ pub struct DetailedScores {
 }
 
 pub struct GitHubScorer {
-    weights: HashMap<String, f64>,
-    contribution_params: HashMap<String, f64>,
-    repo_significance_params: HashMap<String, f64>,
-    code_quality_params: HashMap<String, f64>,
-    community_params: HashMap<String, f64>,
+    config: ScoringConfig,
+    components: Vec<Box<dyn ScoringComponent>>,
+    clock: Arc<dyn Clock>,
     token: Option<String>,
 }
 
 impl Default for GitHubScorer {
     fn default() -> Self {
-        let mut weights = HashMap::new();
-        weights.insert("contribution_weight".to_string(), 0.35);
-        weights.insert("repo_significance".to_string(), 0.30);
-        weights.insert("code_quality".to_string(), 0.20);
-        weights.insert("community_engagement".to_string(), 0.15);
-
-        let mut contribution_params = HashMap::new();
-        contribution_params.insert("commit_frequency".to_string(), 0.35);
-        contribution_params.insert("commit_recency".to_string(), 0.25);
-        contribution_params.insert("issue_resolution".to_string(), 0.20);
-        contribution_params.insert("pr_acceptance".to_string(), 0.20);
-
-        let mut repo_significance_params = HashMap::new();
-        repo_significance_params.insert("stars".to_string(), 0.30);
-        repo_significance_params.insert("forks".to_string(), 0.25);
-        repo_significance_params.insert("repo_activity".to_string(), 0.25);
-        repo_significance_params.insert("ecosystem_impact".to_string(), 0.20);
-
-        let mut code_quality_params = HashMap::new();
-        code_quality_params.insert("code_review_participation".to_string(), 0.30);
-        code_quality_params.insert("documentation".to_string(), 0.30);
-        code_quality_params.insert("commit_quality".to_string(), 0.40);
-
-        let mut community_params = HashMap::new();
-        community_params.insert("discussions".to_string(), 0.30);
-        community_params.insert("project_diversity".to_string(), 0.40);
-        community_params.insert("maintainer_roles".to_string(), 0.30);
-
+        let config = ScoringConfig::default();
         Self {
-            weights,
-            contribution_params,
-            repo_significance_params,
-            code_quality_params,
-            community_params,
+            components: builtin_components(&config),
+            config,
+            clock: Arc::new(SystemClock),
             token: None,
         }
     }
@@ -129,215 +257,142 @@ impl GitHubScorer {
     }
 
     pub fn with_token(token: String) -> Self {
-        let mut scorer = Self::default();
-        scorer.token = Some(token);
-        scorer
+        Self {
+            token: Some(token),
+            ..Self::default()
+        }
     }
 
-    pub fn calculate_score(&self, user: &GitHubUser) -> Result<DetailedScores, ScoringError> {
-        let contribution_score = self.score_contribution_weight(user)?;
-        let repo_score = self.score_repo_significance(user)?;
-        let quality_score = self.score_code_quality(user)?;
-        let community_score = self.score_community_engagement(user)?;
-
-        let component_scores = ScoreComponents {
-            contribution_weight: contribution_score.0,
-            repo_significance: repo_score.0,
-            code_quality: quality_score.0,
-            community_engagement: community_score.0,
-        };
-
-        let mut detailed_components = HashMap::new();
-        detailed_components.insert("contribution_weight".to_string(), contribution_score.1);
-        detailed_components.insert("repo_significance".to_string(), repo_score.1);
-        detailed_components.insert("code_quality".to_string(), quality_score.1);
-        detailed_components.insert("community_engagement".to_string(), community_score.1);
-
-        let final_score = self.weights.iter().fold(0.0, |acc, (key, weight)| {
-            acc + weight * match key.as_str() {
-                "contribution_weight" => contribution_score.0,
-                "repo_significance" => repo_score.0,
-                "code_quality" => quality_score.0,
-                "community_engagement" => community_score.0,
-                _ => 0.0,
-            }
-        });
+    /// The token given to [`GitHubScorer::with_token`]. Scoring makes no
+    /// requests itself; fetching goes through `GitHubClient`.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
 
-        Ok(DetailedScores {
-            final_score,
-            component_scores,
-            detailed_components,
+    /// Builds a scorer from a config, rejecting it if it doesn't validate.
+    pub fn from_config(config: ScoringConfig) -> Result<Self, ScoringError> {
+        config.validate()?;
+        Ok(Self {
+            components: builtin_components(&config),
+            config,
+            clock: Arc::new(SystemClock),
+            token: None,
         })
     }
 
-    fn score_contribution_weight(&self, user: &GitHubUser) -> Result<(f64, HashMap<String, f64>), ScoringError> {
-        let now = Utc::now();
-        let six_months_ago = now - Duration::days(180);
+    /// Sets the clock `calculate_score` takes its reference time from.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
 
-        let commit_events: Vec<&Event> = user.events
-            .iter()
-            .filter(|e| e.r#type == "PushEvent")
-            .collect();
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
 
-        let recent_commits: Vec<&Event> = commit_events
-            .iter()
-            .filter(|e| e.created_at > six_months_ago)
-            .copied()
-            .collect();
+    /// Adds a component to the score, replacing any existing component with
+    /// the same name (built-in ones included).
+    pub fn with_component(mut self, component: impl ScoringComponent + 'static) -> Self {
+        self.register_component(Box::new(component));
+        self
+    }
 
-        let commit_frequency = (recent_commits.len() as f64 / 100.0).min(1.0);
+    pub fn register_component(&mut self, component: Box<dyn ScoringComponent>) {
+        match self.components.iter_mut().find(|c| c.name() == component.name()) {
+            Some(existing) => *existing = component,
+            None => self.components.push(component),
+        }
+    }
 
-        let recency_score = if !recent_commits.is_empty() {
-            let days_ago: Vec<i64> = recent_commits
-                .iter()
-                .map(|e| (now - e.created_at).num_days())
-                .collect();
-            let avg_days = days_ago.iter().sum::<i64>() as f64 / days_ago.len() as f64;
-            1.0 - (avg_days / 180.0).min(1.0)
-        } else {
-            0.0
-        };
+    pub fn component_names(&self) -> Vec<&str> {
+        self.components.iter().map(|c| c.name()).collect()
+    }
 
-        let issue_events: Vec<&Event> = user.events
+    /// Identifies the model that produced a score: the algorithm version and a
+    /// fingerprint of the config and the registered components.
+    pub fn model_version(&self) -> String {
+        let components: Vec<_> = self.components
             .iter()
-            .filter(|e| e.r#type == "IssuesEvent" && 
-                e.payload.as_ref().map_or(false, |p| p.action.as_deref() == Some("closed")))
+            .map(|c| serde_json::json!([c.name(), c.weight()]))
             .collect();
-
-        let total_issues = user.events
-            .iter()
-            .filter(|e| e.r#type == "IssuesEvent")
-            .count();
-
-        let issue_resolution = if total_issues > 0 {
-            issue_events.len() as f64 / total_issues as f64
-        } else {
-            0.0
-        };
-
-        let merged_prs = user.pull_requests
-            .iter()
-            .filter(|pr| pr.merged_at.is_some())
-            .count();
-
-        let pr_acceptance = if !user.pull_requests.is_empty() {
-            merged_prs as f64 / user.pull_requests.len() as f64
-        } else {
-            0.0
-        };
-
-        let mut component_scores = HashMap::new();
-        component_scores.insert("commit_frequency".to_string(), commit_frequency);
-        component_scores.insert("commit_recency".to_string(), recency_score);
-        component_scores.insert("issue_resolution".to_string(), issue_resolution.min(1.0));
-        component_scores.insert("pr_acceptance".to_string(), pr_acceptance.min(1.0));
-
-        let weighted_score = self.contribution_params.iter().fold(0.0, |acc, (key, weight)| {
-            acc + weight * component_scores.get(key).unwrap_or(&0.0)
+        let model = serde_json::json!({
+            "config": self.config,
+            "components": components,
         });
-
-        Ok((weighted_score, component_scores))
+        format!("{}-{}", ALGORITHM_VERSION, fingerprint(&model))
     }
 
-    fn score_repo_significance(&self, user: &GitHubUser) -> Result<(f64, HashMap<String, f64>), ScoringError> {
-        if user.repositories.is_empty() {
-            return Ok((0.0, HashMap::new()));
-        }
-
-        let total_stars: u32 = user.repositories.iter().map(|r| r.stargazers_count).sum();
-        let total_forks: u32 = user.repositories.iter().map(|r| r.forks_count).sum();
-
-        let star_score = (total_stars as f64).ln_1p() / 1000.0_f64.ln_1p();
-        let fork_score = (total_forks as f64).ln_1p() / 500.0_f64.ln_1p();
+    pub fn config(&self) -> &ScoringConfig {
+        &self.config
+    }
 
-        let now = Utc::now();
-        let active_repos = user.repositories
-            .iter()
-            .filter(|r| (now - r.updated_at).num_days() <= 180)
-            .count();
+    /// Counts only distinct commits in pushes, so force-pushes and branch
+    /// syncs don't inflate commit numbers.
+    pub fn with_distinct_commits_only(mut self, distinct_only: bool) -> Self {
+        self.config.distinct_commits_only = distinct_only;
+        self
+    }
 
-        let activity_score = active_repos as f64 / user.repositories.len() as f64;
-        let ecosystem_score = (star_score + fork_score) / 2.0;
+    pub fn distinct_commits_only(&self) -> bool {
+        self.config.distinct_commits_only
+    }
 
-        let mut component_scores = HashMap::new();
-        component_scores.insert("stars".to_string(), star_score.min(1.0));
-        component_scores.insert("forks".to_string(), fork_score.min(1.0));
-        component_scores.insert("repo_activity".to_string(), activity_score.min(1.0));
-        component_scores.insert("ecosystem_impact".to_string(), ecosystem_score.min(1.0));
+    /// Sets how quickly commits, PRs, issues and repository updates lose
+    /// weight as they age. Fails if the resulting config is invalid.
+    pub fn with_time_decay(mut self, time_decay: TimeDecay) -> Result<Self, ScoringError> {
+        self.config.time_decay = time_decay;
+        self.config.validate()?;
+        Ok(self)
+    }
 
-        let weighted_score = self.repo_significance_params.iter().fold(0.0, |acc, (key, weight)| {
-            acc + weight * component_scores.get(key).unwrap_or(&0.0)
-        });
+    pub fn time_decay(&self) -> &TimeDecay {
+        &self.config.time_decay
+    }
 
-        Ok((weighted_score, component_scores))
+    /// Scores `user` as of the scorer's clock.
+    pub fn calculate_score(&self, user: &GitHubUser) -> Result<DetailedScores, ScoringError> {
+        self.calculate_score_at(user, self.clock.now())
     }
 
-    fn score_code_quality(&self, user: &GitHubUser) -> Result<(f64, HashMap<String, f64>), ScoringError> {
-        let review_events = user.events
-            .iter()
-            .filter(|e| e.r#type == "PullRequestReviewEvent")
-            .count();
+    /// Scores `user` as it stood at `as_of`. The final score is the mean of
+    /// every component's score, weighted by `ScoringComponent::weight`.
+    pub fn calculate_score_at(&self, user: &GitHubUser, as_of: DateTime<Utc>) -> Result<DetailedScores, ScoringError> {
+        let user = user.as_of(as_of);
+        let user = user.as_ref();
+        let ctx = ScoringContext::new(&self.config, as_of);
 
-        let review_score = (review_events as f64 / 50.0).min(1.0);
+        let mut component_scores = ScoreComponents::default();
+        let mut detailed_components = HashMap::new();
+        let (mut weighted_sum, mut total_weight) = (0.0, 0.0);
+
+        for component in &self.components {
+            let result = component.score(user, &ctx);
+            if !result.score.is_finite() {
+                return Err(ScoringError::InvalidData(format!(
+                    "component {} produced a score of {}",
+                    component.name(),
+                    result.score
+                )));
+            }
 
-        let doc_repos = user.repositories
-            .iter()
-            .filter(|r| r.description.as_ref().map_or(false, |d| d.len() > 50))
-            .count();
+            component_scores.set(component.name(), result.score);
+            detailed_components.insert(component.name().to_string(), result.breakdown);
+            weighted_sum += component.weight() * result.score;
+            total_weight += component.weight();
+        }
+        detailed_components.insert("time_decay".to_string(), self.config.time_decay.breakdown());
 
-        let doc_score = if !user.repositories.is_empty() {
-            doc_repos as f64 / user.repositories.len() as f64
+        let final_score = if total_weight > 0.0 {
+            weighted_sum / total_weight
         } else {
             0.0
         };
 
-        let commit_quality = 0.7; // Default reasonable quality score
-
-        let mut component_scores = HashMap::new();
-        component_scores.insert("code_review_participation".to_string(), review_score);
-        component_scores.insert("documentation".to_string(), doc_score.min(1.0));
-        component_scores.insert("commit_quality".to_string(), commit_quality);
-
-        let weighted_score = self.code_quality_params.iter().fold(0.0, |acc, (key, weight)| {
-            acc + weight * component_scores.get(key).unwrap_or(&0.0)
-        });
-
-        Ok((weighted_score, component_scores))
-    }
-
-    fn 
-    score_community_engagement(&self, user: &GitHubUser) -> Result<(f64, HashMap<String, f64>), ScoringError> {
-        let discussion_events = user.events
-            .iter()
-            .filter(|e| e.r#type == "IssueCommentEvent" || e.r#type == "CommitCommentEvent")
-            .count();
-
-        let discussion_score = (discussion_events as f64 / 50.0).min(1.0);
-
-        let contributed_repos: std::collections::HashSet<_> = user.events
-            .iter()
-            .filter_map(|e| e.repo.as_ref().map(|r| r.name.clone()))
-            .collect();
-
-        let diversity_score = (contributed_repos.len() as f64 / 10.0).min(1.0);
-
-        let owned_repos = user.repositories
-            .iter()
-            .filter(|r| r.owner.login == user.login)
-            .count();
-
-        let maintainer_score = (owned_repos as f64 / 5.0).min(1.0);
-
-        let mut component_scores = HashMap::new();
-        component_scores.insert("discussions".to_string(), discussion_score);
-        component_scores.insert("project_diversity".to_string(), diversity_score);
-        component_scores.insert("maintainer_roles".to_string(), maintainer_score);
-
-        let weighted_score = self.community_params.iter().fold(0.0, |acc, (key, weight)| {
-            acc + weight * component_scores.get(key).unwrap_or(&0.0)
-        });
-
-        Ok((weighted_score, component_scores))
+        Ok(DetailedScores {
+            final_score,
+            component_scores,
+            detailed_components,
+        })
     }
 
     pub fn rate_score(&self, score: f64) -> &'static str {
@@ -358,7 +413,7 @@ impl GitHubScorer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
+    use crate::scoring::{ComponentResult, FixedClock};
 
     #[test]
     fn test_score_rating() {
@@ -377,9 +432,214 @@ mod tests {
             repositories: vec![],
             events: vec![],
             pull_requests: vec![],
+            contributions: None,
         };
 
         let score = scorer.calculate_score(&empty_user).unwrap();
         assert!(score.final_score < 0.1);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_pr_acceptance_ignores_open_and_counts_external() {
+        let pr = |state, merged: bool, repo: &str| PullRequest {
+            merged_at: merged.then(Utc::now),
+            state,
+            repository: Some(repo.to_string()),
+            created_at: None,
+        };
+        let user = GitHubUser {
+            login: "octo".to_string(),
+            repositories: vec![],
+            events: vec![],
+            pull_requests: vec![
+                pr(PullRequestState::Merged, true, "rust-lang/rust"),
+                pr(PullRequestState::Closed, true, "octo/spoon"),
+                pr(PullRequestState::Closed, false, "tokio-rs/tokio"),
+                pr(PullRequestState::Open, false, "serde-rs/serde"),
+            ],
+            contributions: None,
+        };
+
+        let score = GitHubScorer::new().calculate_score(&user).unwrap();
+        let contribution = &score.detailed_components["contribution_weight"];
+        assert!((contribution["pr_acceptance"] - 2.0 / 3.0).abs() < 1e-9);
+        assert!((contribution["external_contributions"] - 1.0 / 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_commit_count_uses_push_payload() {
+        let push = |payload: Option<EventPayload>| Event {
+            r#type: "PushEvent".to_string(),
+            created_at: Utc::now(),
+            repo: None,
+            payload,
+        };
+        let forced = push(Some(EventPayload {
+            size: Some(12),
+            distinct_size: Some(2),
+            ..Default::default()
+        }));
+        assert_eq!(forced.commit_count(false), 12);
+        assert_eq!(forced.commit_count(true), 2);
+
+        let listed = push(Some(EventPayload {
+            commits: Some(vec![
+                Commit { sha: "a".to_string(), message: "one".to_string() },
+                Commit { sha: "b".to_string(), message: "two".to_string() },
+            ]),
+            ..Default::default()
+        }));
+        assert_eq!(listed.commit_count(true), 2);
+        assert_eq!(push(None).commit_count(false), 1);
+
+        let user = GitHubUser {
+            login: "octo".to_string(),
+            repositories: vec![],
+            events: vec![forced.clone(), forced],
+            pull_requests: vec![],
+            contributions: None,
+        };
+        let all = GitHubScorer::new().calculate_score(&user).unwrap();
+        let distinct = GitHubScorer::new()
+            .with_distinct_commits_only(true)
+            .calculate_score(&user)
+            .unwrap();
+        assert!((all.detailed_components["contribution_weight"]["commit_frequency"] - 0.24).abs() < 1e-9);
+        assert!((distinct.detailed_components["contribution_weight"]["commit_frequency"] - 0.04).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_older_commits_decay() {
+        let now = "2025-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let push = |days_ago: i64| Event {
+            r#type: "PushEvent".to_string(),
+            created_at: now - chrono::Duration::days(days_ago),
+            repo: None,
+            payload: Some(EventPayload { size: Some(10), ..Default::default() }),
+        };
+        let user = GitHubUser {
+            login: "octo".to_string(),
+            repositories: vec![],
+            events: vec![push(0), push(180)],
+            pull_requests: vec![],
+            contributions: None,
+        };
+
+        let score = GitHubScorer::new()
+            .with_clock(FixedClock(now))
+            .calculate_score(&user)
+            .unwrap();
+        let contribution = &score.detailed_components["contribution_weight"];
+        assert_eq!(contribution["commit_frequency"], 0.15);
+        assert_eq!(contribution["commit_recency"], 0.75);
+
+        let flat = GitHubScorer::new()
+            .with_time_decay(TimeDecay { floor: 1.0, ..TimeDecay::default() })
+            .unwrap()
+            .calculate_score_at(&user, now)
+            .unwrap();
+        assert_eq!(flat.detailed_components["contribution_weight"]["commit_frequency"], 0.2);
+        assert_eq!(flat.detailed_components["time_decay"]["floor"], 1.0);
+    }
+
+    #[test]
+    fn test_score_as_of_ignores_later_activity() {
+        let now = "2025-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let push = |days_ago: i64| Event {
+            r#type: "PushEvent".to_string(),
+            created_at: now - chrono::Duration::days(days_ago),
+            repo: None,
+            payload: Some(EventPayload { size: Some(10), ..Default::default() }),
+        };
+        let user = GitHubUser {
+            login: "octo".to_string(),
+            repositories: vec![],
+            events: vec![push(0), push(180)],
+            pull_requests: vec![PullRequest {
+                merged_at: Some(now),
+                state: PullRequestState::Merged,
+                repository: Some("rust-lang/rust".to_string()),
+                created_at: Some(now - chrono::Duration::days(10)),
+            }],
+            contributions: None,
+        };
+        let scorer = GitHubScorer::new().with_clock(FixedClock(now));
+
+        let today = scorer.calculate_score(&user).unwrap();
+        assert_eq!(today.final_score, scorer.calculate_score(&user).unwrap().final_score);
+
+        // Half a year back only the older push and no PRs existed yet
+        let earlier = scorer.calculate_score_at(&user, now - chrono::Duration::days(180)).unwrap();
+        let contribution = &earlier.detailed_components["contribution_weight"];
+        assert_eq!(contribution["commit_frequency"], 0.1);
+        assert_eq!(contribution["commit_recency"], 1.0);
+        assert_eq!(contribution["pr_acceptance"], 0.0);
+        assert!(earlier.final_score < today.final_score);
+    }
+
+    #[test]
+    fn test_custom_component_is_weighted_in() {
+        struct InternalRepos;
+        impl ScoringComponent for InternalRepos {
+            fn name(&self) -> &str {
+                "internal_repos"
+            }
+            fn weight(&self) -> f64 {
+                1.0
+            }
+            fn score(&self, _user: &GitHubUser, _ctx: &ScoringContext) -> ComponentResult {
+                let mut breakdown = HashMap::new();
+                breakdown.insert("internal_commits".to_string(), 12.0);
+                ComponentResult::new(0.8, breakdown)
+            }
+        }
+
+        let user = GitHubUser {
+            login: "octo".to_string(),
+            repositories: vec![],
+            events: vec![],
+            pull_requests: vec![],
+            contributions: None,
+        };
+        let scorer = GitHubScorer::new().with_component(InternalRepos);
+        assert_eq!(scorer.component_names().len(), 5);
+
+        let score = scorer.calculate_score(&user).unwrap();
+        // The built-in weights sum to 1, so the custom one gets half the total
+        assert!((score.final_score - 0.4).abs() < 1e-9);
+        assert_eq!(score.component_scores.custom["internal_repos"], 0.8);
+        assert_eq!(score.detailed_components["internal_repos"]["internal_commits"], 12.0);
+
+        let json = serde_json::to_value(&score.component_scores).unwrap();
+        assert_eq!(json["internal_repos"], 0.8);
+    }
+
+    #[test]
+    fn test_model_version_tracks_config_and_components() {
+        let version = GitHubScorer::new().model_version();
+        assert_eq!(version, GitHubScorer::new().model_version());
+        assert!(version.starts_with(&format!("{}-", ALGORITHM_VERSION)));
+
+        let decayed = GitHubScorer::new()
+            .with_time_decay(TimeDecay { floor: 0.2, ..TimeDecay::default() })
+            .unwrap();
+        assert_ne!(decayed.model_version(), version);
+        assert!(GitHubScorer::new()
+            .with_time_decay(TimeDecay { floor: 1.5, ..TimeDecay::default() })
+            .is_err());
+
+        struct Flat;
+        impl ScoringComponent for Flat {
+            fn name(&self) -> &str {
+                "flat"
+            }
+            fn weight(&self) -> f64 {
+                0.1
+            }
+            fn score(&self, _user: &GitHubUser, _ctx: &ScoringContext) -> ComponentResult {
+                ComponentResult::default()
+            }
+        }
+        assert_ne!(GitHubScorer::new().with_component(Flat).model_version(), version);
+    }
+}
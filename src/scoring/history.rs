@@ -0,0 +1,151 @@
+//! Score time series, optionally downsampled to one point per week or month.
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryInterval {
+    /// Every computed score.
+    #[default]
+    Raw,
+    /// One point per ISO week, starting Monday.
+    Weekly,
+    /// One point per calendar month.
+    Monthly,
+}
+
+impl FromStr for HistoryInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(Self::Raw),
+            "weekly" | "week" => Ok(Self::Weekly),
+            "monthly" | "month" => Ok(Self::Monthly),
+            other => Err(format!("unknown interval '{}', expected 'raw', 'weekly' or 'monthly'", other)),
+        }
+    }
+}
+
+impl HistoryInterval {
+    /// Start of the period `at` falls in; `at` itself for [`HistoryInterval::Raw`].
+    pub fn period_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let date = at.date_naive();
+        let start = match self {
+            Self::Raw => return at,
+            Self::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Monthly => date.with_day(1).unwrap_or(date),
+        };
+        Utc.from_utc_datetime(&start.and_time(NaiveTime::MIN))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HistoryPoint {
+    /// When the score was computed, or the start of the period for
+    /// downsampled points.
+    pub at: DateTime<Utc>,
+    pub final_score: f64,
+    pub component_scores: BTreeMap<String, f64>,
+    /// Model of the latest score in the period.
+    pub model_version: String,
+    /// Number of computed scores the point stands for.
+    pub samples: usize,
+}
+
+/// Averages the points, which must be oldest first, over each period of
+/// `interval`. Components are averaged over the points that have them.
+pub fn downsample(points: &[HistoryPoint], interval: HistoryInterval) -> Vec<HistoryPoint> {
+    if interval == HistoryInterval::Raw {
+        return points.to_vec();
+    }
+
+    let mut periods: Vec<(DateTime<Utc>, Vec<&HistoryPoint>)> = Vec::new();
+    for point in points {
+        let start = interval.period_start(point.at);
+        match periods.last_mut() {
+            Some((period, members)) if *period == start => members.push(point),
+            _ => periods.push((start, vec![point])),
+        }
+    }
+
+    periods
+        .into_iter()
+        .map(|(start, members)| {
+            let samples = members.iter().map(|p| p.samples).sum::<usize>().max(1);
+            let final_score =
+                members.iter().map(|p| p.final_score * p.samples as f64).sum::<f64>() / samples as f64;
+
+            let mut totals: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+            for point in &members {
+                for (name, score) in &point.component_scores {
+                    let total = totals.entry(name.clone()).or_insert((0.0, 0));
+                    total.0 += score * point.samples as f64;
+                    total.1 += point.samples;
+                }
+            }
+            let component_scores = totals
+                .into_iter()
+                .map(|(name, (sum, count))| (name, sum / count.max(1) as f64))
+                .collect();
+
+            HistoryPoint {
+                at: start,
+                final_score,
+                component_scores,
+                model_version: members.last().map(|p| p.model_version.clone()).unwrap_or_default(),
+                samples,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(at: &str, final_score: f64, model_version: &str) -> HistoryPoint {
+        HistoryPoint {
+            at: at.parse().unwrap(),
+            final_score,
+            component_scores: BTreeMap::from([("code_quality".to_string(), final_score / 100.0)]),
+            model_version: model_version.to_string(),
+            samples: 1,
+        }
+    }
+
+    #[test]
+    fn test_downsample_weekly_and_monthly() {
+        let points = vec![
+            point("2025-05-26T10:00:00Z", 40.0, "1-a"), // Monday
+            point("2025-06-01T23:00:00Z", 50.0, "1-b"), // Sunday, same week
+            point("2025-06-02T08:00:00Z", 60.0, "1-b"), // next Monday
+        ];
+
+        let weekly = downsample(&points, HistoryInterval::Weekly);
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].at, "2025-05-26T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(weekly[0].final_score, 45.0);
+        assert_eq!(weekly[0].component_scores["code_quality"], 0.45);
+        assert_eq!(weekly[0].model_version, "1-b");
+        assert_eq!(weekly[0].samples, 2);
+        assert_eq!(weekly[1].final_score, 60.0);
+
+        let monthly = downsample(&points, HistoryInterval::Monthly);
+        assert_eq!(monthly.len(), 2);
+        assert_eq!(monthly[0].at, "2025-05-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(monthly[1].at, "2025-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(monthly[1].final_score, 55.0);
+
+        // Merged points count with the number of scores they stand for
+        let merged = downsample(&[weekly[0].clone(), weekly[1].clone()], HistoryInterval::Monthly);
+        assert_eq!(merged[0].samples, 2);
+
+        assert_eq!(downsample(&points, HistoryInterval::Raw), points);
+        assert_eq!("Monthly".parse::<HistoryInterval>(), Ok(HistoryInterval::Monthly));
+        assert!("daily".parse::<HistoryInterval>().is_err());
+    }
+}
@@ -1,2 +1,23 @@
+mod clock;
+mod commit_quality;
+mod compare;
+mod components;
+mod config;
+mod decay;
 mod github_score;
-pub use github_score::*; 
\ No newline at end of file
+mod history;
+mod profiles;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use commit_quality::CommitMessageStats;
+pub use compare::{
+    compare, Comparison, ComponentComparison, Distinction, RankedUser, DISTINCTION_THRESHOLD, MAX_DISTINCTIONS,
+};
+pub use components::{
+    builtin_components, CodeQuality, CommunityEngagement, ComponentResult, ContributionWeight,
+    RepoSignificance, ScoringComponent, ScoringContext,
+};
+pub use config::{ScoringConfig, Thresholds};
+pub use decay::{DecayFunction, TimeDecay};
+pub use github_score::*;
+pub use history::{downsample, HistoryInterval, HistoryPoint};
+pub use profiles::{ScoringProfile, ScoringProfiles, DEFAULT_PROFILE}; 
\ No newline at end of file
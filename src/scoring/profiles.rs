@@ -0,0 +1,276 @@
+use super::{GitHubScorer, ScoringConfig, ScoringError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named scoring model. The version changes whenever the config does, so
+/// scores computed under an older version of a profile can be told apart.
+pub struct ScoringProfile {
+    pub name: String,
+    pub version: String,
+    /// [`GitHubScorer::model_version`] of the scorer.
+    pub model_version: String,
+    pub scorer: GitHubScorer,
+}
+
+impl ScoringProfile {
+    pub fn new(name: &str, config: ScoringConfig) -> Result<Self, ScoringError> {
+        let version = config.version();
+        let scorer = GitHubScorer::from_config(config)?;
+        Ok(Self {
+            name: name.to_string(),
+            version,
+            model_version: scorer.model_version(),
+            scorer,
+        })
+    }
+}
+
+/// The set of scoring profiles the API can serve.
+///
+/// Profiles are read from `*.toml` and `*.json` files in a directory, named
+/// after the file stem, and applied over the base config. A `default`
+/// profile always exists: it comes from `default.toml` when the directory has
+/// one and is the base config otherwise.
+pub struct ScoringProfiles {
+    dir: Option<PathBuf>,
+    base: ScoringConfig,
+    profiles: RwLock<Arc<HashMap<String, Arc<ScoringProfile>>>>,
+}
+
+impl ScoringProfiles {
+    /// A single `default` profile built from `base`.
+    pub fn single(base: ScoringConfig) -> Result<Self, ScoringError> {
+        let profiles = build_profiles(None, &base)?;
+        Ok(Self {
+            dir: None,
+            base,
+            profiles: RwLock::new(Arc::new(profiles)),
+        })
+    }
+
+    /// Loads every profile in `dir`; fails if any of them is invalid.
+    pub fn from_dir(dir: impl Into<PathBuf>, base: ScoringConfig) -> Result<Self, ScoringError> {
+        let dir = dir.into();
+        let profiles = build_profiles(Some(&dir), &base)?;
+        Ok(Self {
+            dir: Some(dir),
+            base,
+            profiles: RwLock::new(Arc::new(profiles)),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<ScoringProfile>, ScoringError> {
+        self.snapshot()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ScoringError::UnknownProfile(name.to_string()))
+    }
+
+    pub fn default_profile(&self) -> Arc<ScoringProfile> {
+        self.get(DEFAULT_PROFILE).expect("the default profile is always loaded")
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.snapshot().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn snapshot(&self) -> Arc<HashMap<String, Arc<ScoringProfile>>> {
+        self.profiles.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Re-reads the profile directory. If any profile fails to load the
+    /// current set is kept and the error returned.
+    pub fn reload(&self) -> Result<(), ScoringError> {
+        let profiles = build_profiles(self.dir.as_deref(), &self.base)?;
+        *self.profiles.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(profiles);
+        Ok(())
+    }
+
+    /// Reloads the profiles on SIGHUP and whenever a file in the profile
+    /// directory is added, removed or modified, checking every `poll_interval`.
+    pub async fn watch(self: Arc<Self>, poll_interval: Duration) {
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        let mut last_seen = dir_fingerprint(&dir);
+        let mut interval = tokio::time::interval(poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        #[cfg(unix)]
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+            .map_err(|e| println!("Failed to listen for SIGHUP: {}", e))
+            .ok();
+
+        loop {
+            #[cfg(unix)]
+            let hangup_received = async {
+                match hangup.as_mut() {
+                    Some(signal) => signal.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let hangup_received = std::future::pending::<Option<()>>();
+
+            let reason = tokio::select! {
+                _ = interval.tick() => {
+                    let fingerprint = dir_fingerprint(&dir);
+                    if fingerprint == last_seen {
+                        continue;
+                    }
+                    last_seen = fingerprint;
+                    "profile directory changed"
+                }
+                _ = hangup_received => {
+                    last_seen = dir_fingerprint(&dir);
+                    "SIGHUP received"
+                }
+            };
+
+            match self.reload() {
+                Ok(()) => println!("Reloaded scoring profiles ({}): {}", reason, self.names().join(", ")),
+                Err(e) => println!("Keeping previous scoring profiles, reload failed ({}): {}", reason, e),
+            }
+        }
+    }
+}
+
+fn build_profiles(
+    dir: Option<&Path>,
+    base: &ScoringConfig,
+) -> Result<HashMap<String, Arc<ScoringProfile>>, ScoringError> {
+    let mut profiles = HashMap::new();
+
+    for path in dir.map(profile_files).transpose()?.unwrap_or_default() {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let profile = ScoringProfile::new(name, ScoringConfig::from_file_over(&path, base)?)?;
+        profiles.insert(name.to_string(), Arc::new(profile));
+    }
+
+    if !profiles.contains_key(DEFAULT_PROFILE) {
+        profiles.insert(
+            DEFAULT_PROFILE.to_string(),
+            Arc::new(ScoringProfile::new(DEFAULT_PROFILE, base.clone())?),
+        );
+    }
+    Ok(profiles)
+}
+
+fn profile_files(dir: &Path) -> Result<Vec<PathBuf>, ScoringError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| ScoringError::ConfigRead {
+        path: dir.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("toml") || ext.eq_ignore_ascii_case("json"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Names, sizes and modification times of the profile files, for spotting changes.
+fn dir_fingerprint(dir: &Path) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    profile_files(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(&path).ok();
+            let len = metadata.as_ref().map_or(0, |m| m.len());
+            let modified = metadata.and_then(|m| m.modified().ok());
+            (path, len, modified)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scoring-profiles-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_profiles_load_and_reload() {
+        let dir = temp_dir("reload");
+        std::fs::write(dir.join("maintainer.toml"), "[thresholds]\nowned_repos = 20\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a profile").unwrap();
+
+        let profiles = ScoringProfiles::from_dir(&dir, ScoringConfig::default()).unwrap();
+        assert_eq!(profiles.names(), vec!["default", "maintainer"]);
+        let maintainer = profiles.get("maintainer").unwrap();
+        assert_eq!(maintainer.scorer.config().thresholds.owned_repos, 20.0);
+        assert_ne!(maintainer.version, profiles.default_profile().version);
+        assert!(matches!(profiles.get("devrel"), Err(ScoringError::UnknownProfile(_))));
+
+        std::fs::write(dir.join("maintainer.toml"), "[thresholds]\nowned_repos = 30\n").unwrap();
+        std::fs::write(dir.join("devrel.json"), r#"{ "thresholds": { "discussions": 10 } }"#).unwrap();
+        profiles.reload().unwrap();
+        assert_eq!(profiles.names(), vec!["default", "devrel", "maintainer"]);
+        assert_ne!(profiles.get("maintainer").unwrap().version, maintainer.version);
+
+        // A broken file leaves the loaded profiles in place
+        std::fs::write(dir.join("devrel.json"), r#"{ "weights": { "code_quality": 2 } }"#).unwrap();
+        assert!(matches!(profiles.reload(), Err(ScoringError::WeightSum { .. })));
+        assert_eq!(profiles.names(), vec!["default", "devrel", "maintainer"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profiles_apply_over_the_base_config() {
+        let dir = temp_dir("base");
+        std::fs::write(dir.join("maintainer.toml"), "[thresholds]\nowned_repos = 20\n").unwrap();
+        std::fs::write(
+            dir.join("reviewer.json"),
+            r#"{ "code_quality": { "code_review_participation": 1.0 } }"#,
+        )
+        .unwrap();
+        let mut base = ScoringConfig { distinct_commits_only: true, ..ScoringConfig::default() };
+        base.time_decay.floor = 0.5;
+        base.thresholds.stars = 200.0;
+
+        let profiles = ScoringProfiles::from_dir(&dir, base.clone()).unwrap();
+        let maintainer = profiles.get("maintainer").unwrap();
+        let config = maintainer.scorer.config();
+        assert!(config.distinct_commits_only);
+        assert_eq!(config.time_decay.floor, 0.5);
+        assert_eq!((config.thresholds.stars, config.thresholds.owned_repos), (200.0, 20.0));
+
+        // A weight table replaces the base table rather than adding to it
+        let reviewer = profiles.get("reviewer").unwrap();
+        assert_eq!(reviewer.scorer.config().code_quality.len(), 1);
+        assert_eq!(reviewer.scorer.config().time_decay, base.time_decay);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_version_is_stable() {
+        let config = ScoringConfig::default();
+        assert_eq!(config.version(), config.clone().version());
+        assert_eq!(config.version().len(), 16);
+
+        let tuned = ScoringConfig { distinct_commits_only: true, ..ScoringConfig::default() };
+        assert_ne!(config.version(), tuned.version());
+    }
+}